@@ -0,0 +1,108 @@
+use std::time::{Duration, Instant};
+
+use rand::{rngs::StdRng, SeedableRng};
+use statemachine::{
+    boxes::{AStarBox, BeamBox, BucketBox, GreedyBox, StaggeredBox, WeightedAStarBox},
+    distances::Manhattan,
+    heuristics::{LinearConflict, WalkingDistance},
+    machine::Machine,
+    pdb::DisjointPatterns,
+    search::{SearchConfig, TieBreak},
+    tileboard::TileBoard,
+    traits::{Solver, StateBox},
+};
+
+const BOARDS: usize = 20;
+const SHUFFLES: usize = 60;
+const LARGE_SHUFFLES: usize = 100;
+const ROUNDS: u32 = 3;
+
+// Times each heuristic box, A* under each tie-break rule and A* with each
+// heuristic on larger boards, all over seeded boards so that runs before and
+// after a change can be compared.
+fn main() {
+    let mut rng = StdRng::seed_from_u64(17);
+    let boards: Vec<TileBoard<3, 3>> = (0..BOARDS)
+        .map(|_| TileBoard::shuffled_with(SHUFFLES, &mut rng))
+        .collect();
+
+    bench::<3, 3, AStarBox<_, _>>("AStarBox", &boards, Manhattan);
+    bench::<3, 3, StaggeredBox<_, _>>("StaggeredBox", &boards, Manhattan);
+    bench::<3, 3, GreedyBox<_, _>>("GreedyBox", &boards, Manhattan);
+    bench::<3, 3, WeightedAStarBox<_, _>>("WeightedAStarBox", &boards, Manhattan);
+    bench::<3, 3, BeamBox<_, _>>("BeamBox", &boards, Manhattan);
+    bench::<3, 3, BucketBox<_, _>>("BucketBox", &boards, Manhattan);
+
+    for tie_break in [
+        TieBreak::HigherG,
+        TieBreak::LowerG,
+        TieBreak::LowerH,
+        TieBreak::Fifo,
+        TieBreak::Lifo,
+    ] {
+        let config = SearchConfig {
+            tie_break: Some(tie_break),
+            ..SearchConfig::default()
+        };
+        bench_with::<3, 3, AStarBox<_, _>>(
+            &format!("{:?}", tie_break),
+            &boards,
+            Manhattan,
+            &config,
+        );
+    }
+
+    let boards: Vec<TileBoard<4, 4>> = (0..BOARDS)
+        .map(|_| TileBoard::shuffled_with(LARGE_SHUFFLES, &mut rng))
+        .collect();
+    bench::<4, 4, AStarBox<_, _>>("Manhattan 4x4", &boards, Manhattan);
+    bench::<4, 4, BucketBox<_, _>>("Bucket 4x4", &boards, Manhattan);
+    bench::<4, 4, AStarBox<_, _>>("LinearConflict 4x4", &boards, LinearConflict);
+
+    // Tables are built up front and handed to every search, so building them
+    // stays out of the timed rounds.
+    let walking = WalkingDistance::<4, 4>::default();
+    bench::<4, 4, AStarBox<_, _>>("WalkingDistance 4x4", &boards, walking);
+    let patterns = DisjointPatterns::<4, 4>::default();
+    bench::<4, 4, AStarBox<_, _>>("DisjointPatterns 4x4", &boards, patterns);
+}
+
+fn bench<const C: usize, const R: usize, SB: StateBox<TileBoard<C, R>>>(
+    name: &str,
+    boards: &[TileBoard<C, R>],
+    heuristic: SB::Heuristic,
+) where
+    SB::Heuristic: Clone,
+{
+    bench_with::<C, R, SB>(name, boards, heuristic, &SearchConfig::default())
+}
+
+fn bench_with<const C: usize, const R: usize, SB: StateBox<TileBoard<C, R>>>(
+    name: &str,
+    boards: &[TileBoard<C, R>],
+    heuristic: SB::Heuristic,
+    config: &SearchConfig,
+) where
+    SB::Heuristic: Clone,
+{
+    let mut expanded = 0;
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..ROUNDS {
+        expanded = 0;
+        let started = Instant::now();
+        for board in boards {
+            let machine = Machine::new(board.clone(), TileBoard::default())
+                .with_config(config.clone())
+                .with_heuristic(heuristic.clone());
+            expanded += Solver::<_>::search::<SB>(machine).stats.expanded;
+        }
+        elapsed += started.elapsed();
+    }
+
+    println!(
+        "{:<20} {:>12.3?} per round, {} expanded",
+        name,
+        elapsed / ROUNDS,
+        expanded
+    );
+}
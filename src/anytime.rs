@@ -0,0 +1,448 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+    time::Instant,
+};
+
+use crate::{
+    boxes::OpenEntry,
+    search::{SearchConfig, SearchOutcome, SearchReport, SearchStats, Solution},
+    traits::{Heuristic, Observer, Solver, State, StateBox, Supply},
+};
+
+pub const DEFAULT_INITIAL_WEIGHT: f64 = 3f64;
+
+pub const DEFAULT_WEIGHT_STEP: f64 = 0.5;
+
+#[derive(Clone)]
+pub struct ARAStar<S: State, Hr: Heuristic<S>> {
+    init_state: S,
+    complete_state: S,
+    heuristic: Hr,
+    initial_weight: f64,
+    weight_step: f64,
+    config: SearchConfig,
+}
+
+// A solution along with how far from the cheapest its cost may still be, as
+// a factor. A bound of 1 means the solution is known to be optimal.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Improvement<S> {
+    pub solution: Solution<S>,
+    pub bound: f64,
+}
+
+// Runs weighted A* searches with a shrinking weight, reusing the work of each
+// one in the next. Every call to `next` keeps searching until it finds a
+// cheaper solution, and the iterator ends once the bound reaches 1 or the
+// config's budget runs out.
+pub struct Improvements<S: State, Hr: Heuristic<S>, O: Observer<S> = ()> {
+    complete_state: S,
+    heuristic: Hr,
+    weight: f64,
+    weight_step: f64,
+    config: SearchConfig,
+    observer: O,
+    started: Instant,
+    stats: SearchStats,
+    costs: HashMap<S, f64>,
+    parents: HashMap<S, S>,
+    depths: HashMap<S, usize>,
+    open: BinaryHeap<Reverse<OpenEntry<S>>>,
+    open_states: HashSet<S>,
+    closed: HashSet<S>,
+    inconsistent: HashSet<S>,
+    best: Option<Improvement<S>>,
+    stopped: Option<SearchOutcome<S>>,
+    finished: bool,
+}
+
+impl<S: State, Hr: Heuristic<S> + Default> ARAStar<S, Hr> {
+    pub fn new(init_state: S, complete_state: S) -> Self {
+        ARAStar::with_heuristic(init_state, complete_state, Hr::default())
+    }
+}
+
+impl<S: State, Hr: Heuristic<S>> ARAStar<S, Hr> {
+    pub fn with_heuristic(init_state: S, complete_state: S, heuristic: Hr) -> Self {
+        ARAStar {
+            init_state,
+            complete_state,
+            heuristic,
+            initial_weight: DEFAULT_INITIAL_WEIGHT,
+            weight_step: DEFAULT_WEIGHT_STEP,
+            config: SearchConfig::default(),
+        }
+    }
+
+    // A step that is not positive would never shrink the weight, so it drops
+    // the weight straight to 1 after the first round instead.
+    pub fn with_weights(mut self, initial_weight: f64, weight_step: f64) -> Self {
+        self.initial_weight = initial_weight.max(1f64);
+        self.weight_step = if weight_step > 0f64 {
+            weight_step
+        } else {
+            f64::INFINITY
+        };
+        self
+    }
+
+    pub fn with_config(mut self, config: SearchConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn solutions(self) -> Improvements<S, Hr> {
+        self.solutions_observed(())
+    }
+
+    pub fn solutions_observed<O: Observer<S>>(self, observer: O) -> Improvements<S, Hr, O> {
+        let mut costs = HashMap::new();
+        costs.insert(self.init_state.clone(), 0f64);
+        let mut open_states = HashSet::new();
+        open_states.insert(self.init_state.clone());
+
+        let mut improvements = Improvements {
+            complete_state: self.complete_state,
+            heuristic: self.heuristic,
+            weight: self.initial_weight,
+            weight_step: self.weight_step,
+            config: self.config,
+            observer,
+            started: Instant::now(),
+            stats: SearchStats::default(),
+            costs,
+            parents: HashMap::new(),
+            depths: HashMap::new(),
+            open: BinaryHeap::new(),
+            open_states,
+            closed: HashSet::new(),
+            inconsistent: HashSet::new(),
+            best: None,
+            stopped: None,
+            finished: false,
+        };
+        improvements.push(self.init_state, 0f64);
+        improvements
+    }
+}
+
+impl<S: State, Hr: Heuristic<S>, O: Observer<S>> Improvements<S, Hr, O> {
+    pub fn best(&self) -> Option<&Improvement<S>> {
+        self.best.as_ref()
+    }
+
+    pub fn stats(&self) -> &SearchStats {
+        &self.stats
+    }
+
+    // Why the search stopped short of proving its best solution optimal: a
+    // limit was hit or it was cancelled. `None` while it can still improve,
+    // and after it ends with a bound of 1 or runs out of states.
+    pub fn outcome(&self) -> Option<&SearchOutcome<S>> {
+        self.stopped.as_ref()
+    }
+
+    fn push(&mut self, state: S, cost: f64) {
+        self.open.push(Reverse(OpenEntry {
+            estimate: cost + self.weight * self.heuristic.estimate(&state),
+            cost,
+            state,
+        }));
+    }
+
+    fn goal_cost(&self) -> f64 {
+        self.costs
+            .get(&self.complete_state)
+            .copied()
+            .unwrap_or(f64::INFINITY)
+    }
+
+    // Expands states until none left open could lead to a cheaper goal under
+    // the current weight. States that get cheaper after being closed are set
+    // aside until the next round rather than expanded again in this one.
+    // Returns false if the budget ran out first.
+    fn improve_path(&mut self) -> bool {
+        loop {
+            match self.open.peek() {
+                Some(Reverse(entry)) if entry.estimate < self.goal_cost() => {}
+                _ => return true,
+            }
+            // Checked before popping, so that whatever is left open still
+            // counts towards the bound.
+            if self.config.cancelled() {
+                self.stopped = Some(SearchOutcome::Cancelled);
+                return false;
+            }
+            if let Some(limit) =
+                self.config
+                    .exceeded(self.stats.expanded, self.open_states.len(), self.started)
+            {
+                self.stopped = Some(SearchOutcome::LimitReached(limit));
+                return false;
+            }
+
+            let Reverse(entry) = self.open.pop().unwrap();
+            if entry.cost > self.costs[&entry.state] || !self.open_states.remove(&entry.state) {
+                continue;
+            }
+
+            let depth = self.depths.get(&entry.state).copied().unwrap_or(0);
+            self.stats.expanded += 1;
+            self.observer.popped(&entry.state, depth);
+            self.closed.insert(entry.state.clone());
+            for (next_state, step_cost) in entry.state.next_weighted() {
+                self.stats.generated += 1;
+                let cost = entry.cost + step_cost;
+                self.observer.generated(&next_state, depth + 1, cost);
+                if self
+                    .costs
+                    .get(&next_state)
+                    .is_some_and(|&known| known <= cost)
+                {
+                    self.stats.duplicates += 1;
+                    self.observer.duplicate(&next_state);
+                    continue;
+                }
+
+                self.costs.insert(next_state.clone(), cost);
+                self.parents.insert(next_state.clone(), entry.state.clone());
+                self.depths.insert(next_state.clone(), depth + 1);
+                if self.closed.contains(&next_state) {
+                    self.inconsistent.insert(next_state);
+                } else {
+                    self.open_states.insert(next_state.clone());
+                    self.push(next_state, cost);
+                }
+            }
+            self.stats.peak_frontier = self.stats.peak_frontier.max(self.open_states.len());
+            self.stats.peak_seen = self.stats.peak_seen.max(self.costs.len());
+        }
+    }
+
+    // The cheapest goal found so far divided by the lowest unweighted estimate
+    // of anything still left to search. Only a finished round also caps it at
+    // the round's weight.
+    fn bound(&self) -> f64 {
+        let goal_cost = self.goal_cost();
+        let lowest = self
+            .open_states
+            .iter()
+            .chain(self.inconsistent.iter())
+            .map(|state| self.costs[state] + self.heuristic.estimate(state))
+            .fold(goal_cost, f64::min);
+        if goal_cost <= lowest {
+            1f64
+        } else {
+            (goal_cost / lowest).max(1f64)
+        }
+    }
+
+    fn path(&self) -> Vec<S> {
+        let mut path = vec![self.complete_state.clone()];
+        while let Some(parent) = self.parents.get(path.last().unwrap()) {
+            path.push(parent.clone());
+        }
+        path.reverse();
+        path
+    }
+
+    fn next_round(&mut self) {
+        self.weight = (self.weight - self.weight_step).max(1f64);
+        self.closed.clear();
+        self.open_states.extend(self.inconsistent.drain());
+        self.open.clear();
+        let open_states: Vec<S> = self.open_states.iter().cloned().collect();
+        for state in open_states {
+            let cost = self.costs[&state];
+            self.push(state, cost);
+        }
+    }
+}
+
+impl<S: State, Hr: Heuristic<S>, O: Observer<S>> Iterator for Improvements<S, Hr, O> {
+    type Item = Improvement<S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
+            // A round cut short by the budget may still have found a cheaper
+            // goal, so that is reported before stopping.
+            let completed = self.improve_path();
+            let goal_cost = self.goal_cost();
+            let bound = if completed {
+                self.bound().min(self.weight)
+            } else {
+                self.bound()
+            };
+            let improved = self
+                .best
+                .as_ref()
+                .map_or(goal_cost.is_finite(), |best| goal_cost < best.solution.cost);
+            self.finished = !completed
+                || bound <= 1f64
+                || (self.open_states.is_empty() && self.inconsistent.is_empty());
+            if !self.finished {
+                self.next_round();
+            }
+
+            if improved {
+                let improvement = Improvement {
+                    solution: Solution {
+                        path: self.path(),
+                        cost: goal_cost,
+                    },
+                    bound,
+                };
+                self.best = Some(improvement.clone());
+                return Some(improvement);
+            }
+            if let Some(best) = &mut self.best {
+                best.bound = bound;
+            }
+        }
+
+        self.stats.elapsed = self.started.elapsed();
+        None
+    }
+}
+
+// Runs until the bound reaches 1 or the budget is spent and reports the best
+// solution found by then. Observers hear about every expansion, but only
+// about the best solution rather than each improvement on the way.
+impl<S: State, Hr: Heuristic<S>> Solver<S> for ARAStar<S, Hr> {
+    type Supplier = ();
+
+    fn search_observed<SB: StateBox<S>, O: Observer<S>>(self, observer: &mut O) -> SearchReport<S>
+    where
+        (): Supply<SB::Heuristic>,
+    {
+        let mut improvements = self.solutions_observed(&mut *observer);
+        for _ in &mut improvements {}
+
+        let outcome = match improvements.best {
+            Some(best) => SearchOutcome::Found(best.solution),
+            None => improvements.stopped.unwrap_or(SearchOutcome::Exhausted),
+        };
+        SearchReport::new(outcome, improvements.stats, improvements.started, observer)
+    }
+
+    // Every improvement in the order it was found, so the last is the best.
+    fn find_all_observed<SB: StateBox<S>, O: Observer<S>>(self, observer: &mut O) -> Vec<Vec<S>>
+    where
+        (): Supply<SB::Heuristic>,
+    {
+        let mut improvements = self.solutions_observed(&mut *observer);
+        let paths = (&mut improvements)
+            .map(|improvement| improvement.solution.path)
+            .collect();
+        let (best, mut stats, started) =
+            (improvements.best, improvements.stats, improvements.started);
+        let depth = match &best {
+            Some(best) => {
+                observer.goal_found(&best.solution);
+                best.solution.path.len() - 1
+            }
+            None => stats.max_depth,
+        };
+        stats.conclude(depth, started);
+        observer.finished(&stats);
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    pub use super::*;
+    use crate::{
+        boxes::UniformCostBox,
+        distances::Euclidian,
+        machine::Machine,
+        search::Limit,
+        testing::{board, boards, is_valid_path, Junction},
+        tileboard::TileBoard,
+    };
+
+    #[test]
+    fn improves_until_optimal_alright() {
+        for init in boards::<3, 3>(5, 40) {
+            let machine = Machine::new(init.clone(), TileBoard::default());
+            let (cheapest, _) = Solver::<_>::find_solution::<UniformCostBox<_>>(machine).unwrap();
+
+            let mut solutions =
+                ARAStar::<_, Euclidian>::new(init.clone(), TileBoard::default()).solutions();
+            let improvements: Vec<_> = (&mut solutions).collect();
+
+            assert!(!improvements.is_empty());
+            assert!(improvements
+                .windows(2)
+                .all(|w| w[1].solution.cost < w[0].solution.cost));
+            assert!(improvements
+                .iter()
+                .all(|i| i.solution.cost <= cheapest.cost * i.bound));
+
+            let best = solutions.best().unwrap();
+            assert_eq!(best.bound, 1f64);
+            assert_eq!(best.solution.cost, cheapest.cost);
+            assert_eq!(best.solution.path.first(), Some(&init));
+            assert!(is_valid_path(&best.solution.path));
+        }
+    }
+
+    #[test]
+    fn stops_when_the_budget_runs_out_alright() {
+        let config = SearchConfig {
+            max_expansions: Some(1),
+            ..SearchConfig::default()
+        };
+        let solver = ARAStar::<_, Euclidian>::new(board::<3, 3>(40), TileBoard::default())
+            .with_config(config);
+
+        let report = Solver::<_>::search::<UniformCostBox<_>>(solver);
+        assert!(matches!(
+            report.outcome,
+            SearchOutcome::Found(_) | SearchOutcome::LimitReached(Limit::Expansions)
+        ));
+        assert_eq!(report.stats.expanded, 1);
+    }
+
+    #[test]
+    fn reports_what_a_cut_short_round_found_alright() {
+        // The first expansion reaches the goal along the dear direct edge, and
+        // the budget runs out before the round can look any further.
+        let config = SearchConfig {
+            max_expansions: Some(1),
+            ..SearchConfig::default()
+        };
+        let mut solutions = ARAStar::<_, Euclidian>::new(Junction(0), Junction(3))
+            .with_config(config)
+            .solutions();
+
+        let first = solutions.next().unwrap();
+        assert_eq!(first.solution.path, vec![Junction(0), Junction(3)]);
+        assert_eq!(first.solution.cost, 10f64);
+        assert!(first.bound > 3f64);
+        assert_eq!(solutions.next(), None);
+        assert_eq!(
+            solutions.outcome(),
+            Some(&SearchOutcome::LimitReached(Limit::Expansions))
+        );
+    }
+
+    #[test]
+    fn finishes_without_a_weight_step_alright() {
+        let init: TileBoard<3, 3> = board(40);
+        let machine = Machine::new(init.clone(), TileBoard::default());
+        let (cheapest, _) = Solver::<_>::find_solution::<UniformCostBox<_>>(machine).unwrap();
+
+        for step in [0f64, -1f64, f64::NAN] {
+            let mut solutions = ARAStar::<_, Euclidian>::new(init.clone(), TileBoard::default())
+                .with_weights(3f64, step)
+                .solutions();
+            for _ in &mut solutions {}
+
+            let best = solutions.best().unwrap();
+            assert_eq!(best.bound, 1f64);
+            assert_eq!(best.solution.cost, cheapest.cost);
+        }
+    }
+}
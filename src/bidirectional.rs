@@ -0,0 +1,376 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Instant,
+};
+
+use crate::{
+    search::{Limit, SearchConfig, SearchOutcome, SearchReport, SearchStats, Solution},
+    traits::{Given, History, Observer, Solver, State, StateBox, Supply},
+};
+
+#[derive(Clone)]
+pub struct Bidirectional<S: State> {
+    init_state: S,
+    complete_state: S,
+    config: SearchConfig,
+}
+
+// Successors the other frontier has already reached, with this side's
+// history up to each.
+type Meetings<S, H> = Vec<(S, H)>;
+
+struct Frontier<S: State, SB: StateBox<S>> {
+    unprocessed_states: SB,
+    seen: HashMap<S, SB::History>,
+    // How many moves from its root the state popped last was.
+    depth: usize,
+    depth_pruned: bool,
+}
+
+impl<S: State, SB: StateBox<S>> Frontier<S, SB> {
+    fn new(state: S, heuristic: SB::Heuristic) -> Self {
+        let mut seen = HashMap::new();
+        seen.insert(state.clone(), SB::History::default());
+        Self {
+            unprocessed_states: SB::init(state, heuristic),
+            seen,
+            depth: 0,
+            depth_pruned: false,
+        }
+    }
+
+    // Pops and expands a single state, returning where it meets the other
+    // frontier. Errs with the reason to stop when a limit is hit or the
+    // search is cancelled.
+    fn expand<O: Observer<S>>(
+        &mut self,
+        other: &Self,
+        config: &SearchConfig,
+        started: Instant,
+        stats: &mut SearchStats,
+        observer: &mut O,
+    ) -> Result<Option<Meetings<S, SB::History>>, SearchOutcome<S>> {
+        let (state, history, cost) = match self.unprocessed_states.pop() {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        self.depth = history.len();
+
+        if config.cancelled() {
+            return Err(SearchOutcome::Cancelled);
+        }
+        let frontier = self.unprocessed_states.len() + other.unprocessed_states.len();
+        if let Some(limit) = config.exceeded(stats.expanded, frontier, started) {
+            return Err(SearchOutcome::LimitReached(limit));
+        }
+        if config.too_deep(history.len()) {
+            self.depth_pruned = true;
+            return Ok(Some(Vec::new()));
+        }
+
+        stats.expanded += 1;
+        stats.max_depth = stats.max_depth.max(history.len() + 1);
+        observer.popped(&state, history.len());
+
+        let mut meetings = Vec::new();
+        for (next_state, step_cost) in state.next_weighted() {
+            let new_history = history.push(state.clone());
+            stats.generated += 1;
+            observer.generated(&next_state, new_history.len(), cost + step_cost);
+            if other.seen.contains_key(&next_state) {
+                meetings.push((next_state.clone(), new_history.clone()));
+            }
+            if self.seen.contains_key(&next_state) {
+                stats.duplicates += 1;
+                observer.duplicate(&next_state);
+            } else {
+                self.seen.insert(next_state.clone(), new_history.clone());
+                self.unprocessed_states
+                    .insert(next_state, new_history, cost + step_cost);
+            }
+        }
+        Ok(Some(meetings))
+    }
+}
+
+impl<S: State> Bidirectional<S> {
+    pub fn new(init_state: S, complete_state: S) -> Self {
+        Bidirectional {
+            init_state,
+            complete_state,
+            config: SearchConfig::default(),
+        }
+    }
+
+    pub fn with_config(self, config: SearchConfig) -> Self {
+        Bidirectional { config, ..self }
+    }
+
+    // Expands the two frontiers in turn. The backward frontier walks `next`
+    // from the complete state, so this relies on every move being reversible.
+    // The first meeting need not be on a shortest path, so when looking for
+    // one it keeps going until the depths the two sides have reached add up
+    // to the shortest join, which holds for boxes that pop shallowest first.
+    // Each side stops at `max_depth` on its own, and joined paths longer than
+    // it are dropped. Returns why it stopped early, if it did.
+    fn expand_both<SB: StateBox<S>, O: Observer<S>>(
+        self,
+        find_all: bool,
+        started: Instant,
+        observer: &mut O,
+    ) -> (Vec<Vec<S>>, SearchStats, Option<SearchOutcome<S>>)
+    where
+        Given<()>: Supply<SB::Heuristic>,
+    {
+        let mut results = Vec::new();
+        let mut found = HashSet::new();
+
+        if self.init_state == self.complete_state {
+            results.push(vec![self.init_state.clone()]);
+            if !find_all {
+                return (results, SearchStats::default(), None);
+            }
+        }
+
+        let config = self.config;
+        let fits = |path: &Vec<S>| config.max_depth.is_none_or(|max| path.len() - 1 <= max);
+        let mut stopped = None;
+        let mut stats = SearchStats::default();
+        let mut forward = Frontier::<S, SB>::new(self.init_state, Given(()).supply());
+        let mut backward = Frontier::<S, SB>::new(self.complete_state, Given(()).supply());
+        loop {
+            let mut progressed = false;
+
+            match forward.expand(&backward, &config, started, &mut stats, observer) {
+                Ok(Some(meetings)) => {
+                    progressed = true;
+                    for (meeting, history) in meetings {
+                        let path = join(history, meeting.clone(), backward.seen[&meeting].clone());
+                        if fits(&path) && found.insert(path.clone()) {
+                            results.push(path);
+                        }
+                    }
+                }
+                Ok(None) => {}
+                Err(outcome) => {
+                    stopped = Some(outcome);
+                    break;
+                }
+            }
+
+            if !find_all && settled(&results, &forward, &backward) {
+                break;
+            }
+
+            match backward.expand(&forward, &config, started, &mut stats, observer) {
+                Ok(Some(meetings)) => {
+                    progressed = true;
+                    for (meeting, history) in meetings {
+                        let path = join(forward.seen[&meeting].clone(), meeting, history);
+                        if fits(&path) && found.insert(path.clone()) {
+                            results.push(path);
+                        }
+                    }
+                }
+                Ok(None) => {}
+                Err(outcome) => {
+                    stopped = Some(outcome);
+                    break;
+                }
+            }
+
+            stats.peak_frontier = stats
+                .peak_frontier
+                .max(forward.unprocessed_states.len() + backward.unprocessed_states.len());
+            stats.peak_seen = stats
+                .peak_seen
+                .max(forward.seen.len() + backward.seen.len());
+
+            if !progressed || (!find_all && settled(&results, &forward, &backward)) {
+                break;
+            }
+        }
+
+        if !find_all {
+            if let Some(shortest) = results.iter().min_by_key(|path| path.len()) {
+                results = vec![shortest.clone()];
+            }
+        }
+
+        if stopped.is_none() && (forward.depth_pruned || backward.depth_pruned) {
+            stopped = Some(SearchOutcome::LimitReached(Limit::Depth));
+        }
+        (results, stats, stopped)
+    }
+}
+
+// Whether no path left to find could be shorter than the shortest one joined
+// so far.
+fn settled<S: State, SB: StateBox<S>>(
+    results: &[Vec<S>],
+    forward: &Frontier<S, SB>,
+    backward: &Frontier<S, SB>,
+) -> bool {
+    results
+        .iter()
+        .map(|path| path.len() - 1)
+        .min()
+        .is_some_and(|shortest| shortest <= forward.depth + backward.depth)
+}
+
+fn join<S: State, H: History<S>>(forward: H, meeting: S, backward: H) -> Vec<S> {
+    let mut path: Vec<S> = forward.into();
+    path.push(meeting);
+    let mut rest: Vec<S> = backward.into();
+    rest.reverse();
+    path.extend(rest);
+    path
+}
+
+// The backward half of a path was found by expanding in reverse, so its cost
+// is recomputed move by move in the forward direction.
+fn path_cost<S: State>(path: &[S]) -> f64 {
+    path.windows(2)
+        .map(|w| {
+            w[0].next_weighted()
+                .into_iter()
+                .find(|(next_state, _)| *next_state == w[1])
+                .map(|(_, cost)| cost)
+                .unwrap_or(f64::INFINITY)
+        })
+        .sum()
+}
+
+// A heuristic would have to measure the backward frontier's distance to the
+// initial state rather than the complete one, so only boxes that go without
+// one, such as `BFSBox`, can search from both ends.
+impl<S: State> Solver<S> for Bidirectional<S> {
+    type Supplier = Given<()>;
+
+    fn search_observed<SB: StateBox<S>, O: Observer<S>>(self, observer: &mut O) -> SearchReport<S>
+    where
+        Given<()>: Supply<SB::Heuristic>,
+    {
+        let started = Instant::now();
+        let (mut results, stats, stopped) = self.expand_both::<SB, _>(false, started, observer);
+        let outcome = match results.pop() {
+            Some(path) => {
+                let cost = path_cost(&path);
+                SearchOutcome::Found(Solution { path, cost })
+            }
+            None => stopped.unwrap_or(SearchOutcome::Exhausted),
+        };
+        SearchReport::new(outcome, stats, started, observer)
+    }
+
+    fn find_all_observed<SB: StateBox<S>, O: Observer<S>>(self, observer: &mut O) -> Vec<Vec<S>>
+    where
+        Given<()>: Supply<SB::Heuristic>,
+    {
+        let started = Instant::now();
+        let (results, mut stats, _) = self.expand_both::<SB, _>(true, started, observer);
+        for path in &results {
+            let cost = path_cost(path);
+            observer.goal_found(&Solution {
+                path: path.clone(),
+                cost,
+            });
+        }
+        stats.conclude(stats.max_depth, started);
+        observer.finished(&stats);
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    pub use super::*;
+    use crate::{
+        boxes::BFSBox,
+        hanoi::Hanoi,
+        machine::Machine,
+        search::{Cancellation, Limit, SearchConfig, SearchOutcome},
+        testing::is_valid_path,
+    };
+
+    // A ring of seven searched from 0 to 4. With neighbours in this order the
+    // two sides first meet along 0 -> 1 -> 2 -> 3 -> 4, a move longer than
+    // going round the other way through 6 and 5.
+    #[derive(Clone, PartialEq, Eq, Hash, Debug)]
+    struct Ring(usize);
+
+    impl State for Ring {
+        type Point = ();
+
+        fn next(&self) -> Vec<Self> {
+            let next = match self.0 {
+                0 => [1, 6],
+                1 => [0, 2],
+                2 => [3, 1],
+                3 => [2, 4],
+                4 => [3, 5],
+                5 => [6, 4],
+                _ => [0, 5],
+            };
+            next.iter().map(|&n| Ring(n)).collect()
+        }
+
+        fn differences(&self) -> Vec<(Self::Point, Self::Point)> {
+            Vec::new()
+        }
+    }
+
+    #[test]
+    fn meets_in_the_middle_alright() {
+        let machine = Machine::new(Hanoi::<6>::new(), Hanoi::solved());
+        let (forward_path, forward_checks) =
+            Solver::<_>::find_one_with_checks::<BFSBox<_>>(machine.clone()).unwrap();
+        let (path, checks) =
+            Solver::<_>::find_one_with_checks::<BFSBox<_>>(machine.bidirectional()).unwrap();
+
+        assert_eq!(path.first(), Some(&Hanoi::new()));
+        assert_eq!(path.last(), Some(&Hanoi::solved()));
+        assert!(is_valid_path(&path));
+        assert_eq!(path.len(), forward_path.len());
+        assert!(checks < forward_checks);
+    }
+
+    #[test]
+    fn finds_a_shortest_path_past_the_first_meeting_alright() {
+        let solver = Bidirectional::new(Ring(0), Ring(4));
+        let path = Solver::<_>::find_one::<BFSBox<_>>(solver).unwrap();
+        assert_eq!(path, vec![Ring(0), Ring(6), Ring(5), Ring(4)]);
+    }
+
+    #[test]
+    fn respects_the_config_alright() {
+        let search = |config: SearchConfig| {
+            let machine = Machine::new(Hanoi::<6>::new(), Hanoi::solved()).with_config(config);
+            Solver::<_>::search::<BFSBox<_>>(machine.bidirectional())
+        };
+
+        let report = search(SearchConfig {
+            max_expansions: Some(3),
+            ..SearchConfig::default()
+        });
+        assert_eq!(
+            report.outcome,
+            SearchOutcome::LimitReached(Limit::Expansions)
+        );
+        assert_eq!(report.stats.expanded, 3);
+
+        let report = search(SearchConfig {
+            max_depth: Some(10),
+            ..SearchConfig::default()
+        });
+        assert_eq!(report.outcome, SearchOutcome::LimitReached(Limit::Depth));
+
+        let cancellation = Cancellation::new();
+        cancellation.cancel();
+        let report = search(SearchConfig {
+            cancellation: Some(cancellation),
+            ..SearchConfig::default()
+        });
+        assert_eq!(report.outcome, SearchOutcome::Cancelled);
+    }
+}
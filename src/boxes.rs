@@ -1,200 +1,1050 @@
-use std::{
-    cmp::Reverse,
-    collections::{BinaryHeap, VecDeque},
-    marker::PhantomData,
-};
+use std::{cmp::Ordering, collections::VecDeque, mem};
 
 use crate::{
+    search::{SearchConfig, Tie, TieBreak},
     sharedlist::SharedList,
-    traits::{Distance, State, StateBox},
+    traits::{Heuristic, History, State, StateBox},
 };
 
-pub struct BFSBox<S: State> {
-    inner: VecDeque<(S, SharedList<S>)>,
+pub struct BFSBox<S: State, H: History<S> = SharedList<S>> {
+    inner: VecDeque<(S, H, f64)>,
 }
 
-pub struct DFSBox<S: State> {
-    inner: Vec<(S, SharedList<S>)>,
+pub struct DFSBox<S: State, H: History<S> = SharedList<S>> {
+    inner: Vec<(S, H, f64)>,
 }
 
-pub struct AStarBox<S: State<Point = Diff>, D: Distance<Point = Diff>, Diff> {
-    inner: BinaryHeap<Reverse<AStarEntry<S, D, Diff>>>,
+pub struct UniformCostBox<S: State, H: History<S> = SharedList<S>> {
+    inner: Queue<ScoredEntry<S, H>>,
+    ties: Ties,
 }
 
-pub struct StaggeredBox<S: State<Point = Diff>, D: Distance<Point = Diff>, Diff> {
-    inner: Vec<BinaryHeap<Reverse<ScoredEntry<S, D, Diff>>>>,
+pub struct AStarBox<S: State, Hr: Heuristic<S>, H: History<S> = SharedList<S>> {
+    inner: Queue<ScoredEntry<S, H>>,
+    ties: Ties,
+    heuristic: Hr,
+    parent: Option<Parent>,
 }
 
-struct AStarEntry<S: State<Point = Diff>, D: Distance<Point = Diff>, Diff> {
-    state: S,
-    history: SharedList<S>,
-    _dist: PhantomData<D>,
+pub struct StaggeredBox<S: State, Hr: Heuristic<S>, H: History<S> = SharedList<S>> {
+    inner: Vec<Queue<ScoredEntry<S, H>>>,
+    // Every layer below this one is empty.
+    shallowest: usize,
+    len: usize,
+    ties: Ties,
+    heuristic: Hr,
+    parent: Option<Parent>,
+}
+
+// Keeps at most `width` states per depth, dropping the ones the heuristic
+// likes least. Layers are drained shallowest first and best first, so the
+// search walks down the beam much like `StaggeredBox` but in bounded memory.
+pub struct BeamBox<S: State, Hr: Heuristic<S>, H: History<S> = SharedList<S>> {
+    width: usize,
+    inner: Vec<Queue<ScoredEntry<S, H>>>,
+    draining: Vec<ScoredEntry<S, H>>,
+    ties: Ties,
+    heuristic: Hr,
+    parent: Option<Parent>,
+}
+
+// Ignores the cost so far and always pops whichever state looks closest to
+// the goal, at any depth.
+pub struct GreedyBox<S: State, Hr: Heuristic<S>, H: History<S> = SharedList<S>> {
+    inner: Queue<ScoredEntry<S, H>>,
+    ties: Ties,
+    heuristic: Hr,
+    parent: Option<Parent>,
+}
+
+// Scores with g + w * h. With an admissible heuristic, whatever it finds
+// costs at most `weight` times the cheapest solution.
+pub struct WeightedAStarBox<S: State, Hr: Heuristic<S>, H: History<S> = SharedList<S>> {
+    weight: f64,
+    inner: Queue<ScoredEntry<S, H>>,
+    ties: Ties,
+    heuristic: Hr,
+    parent: Option<Parent>,
+}
+
+// A* over integer costs and heuristics, with entries filed by f and then as
+// the tie-break rule needs, so that pushing and popping never compare scores
+// unless the rule is a custom one. Fractional values are rounded, so only use
+// it when every step cost and estimate is whole.
+pub struct BucketBox<S: State, Hr: Heuristic<S>, H: History<S> = SharedList<S>> {
+    buckets: Vec<Bucket<S, H>>,
+    lowest: usize,
+    len: usize,
+    ties: Ties,
+    heuristic: Hr,
+    parent: Option<Parent>,
+}
+
+// Every entry sharing one f. Rules that go by g file entries by g and pop the
+// lowest or highest g oldest first; within one f a lower h is a higher g.
+// Fifo and Lifo keep a single queue in insertion order, and custom rules scan
+// that queue with their comparator on every pop.
+struct Bucket<S: State, H: History<S>> {
+    by_cost: Vec<VecDeque<ScoredEntry<S, H>>>,
+    lowest: usize,
+    len: usize,
+}
+
+pub const DEFAULT_BEAM_WIDTH: usize = 100;
+
+pub const DEFAULT_HEURISTIC_WEIGHT: f64 = 2f64;
+
+// An entry in the open lists that searches running outside of a box keep for
+// themselves, ordered by its estimate alone.
+pub(crate) struct OpenEntry<S> {
+    pub(crate) estimate: f64,
+    pub(crate) cost: f64,
+    pub(crate) state: S,
 }
 
-struct ScoredEntry<S: State<Point = Diff>, D: Distance<Point = Diff>, Diff> {
+// The heuristic is worked out once on insert, and `score` is whatever the
+// owning box orders by, so comparisons never touch the state itself.
+struct ScoredEntry<S: State, H: History<S>> {
     state: S,
-    history: SharedList<S>,
-    _dist: PhantomData<D>,
+    history: H,
+    cost: f64,
+    heuristic: f64,
+    score: f64,
+    sequence: u64,
 }
 
-impl<S: State<Point = Diff>, D: Distance<Point = Diff>, Diff> AStarEntry<S, D, Diff> {
-    fn score(&self) -> f64 {
-        self.state
-            .differences()
-            .into_iter()
-            .map(|(real, found)| D::distance(real, found))
-            .sum::<f64>()
-            + self.history.len() as f64
-    }
+// A binary heap that pops whatever the comparator it is handed puts first,
+// so that boxes can keep their tie-break rule once instead of in every entry.
+struct Queue<T> {
+    entries: Vec<T>,
+}
+
+// The cost and estimate of the entry popped last, which `StateBox::insert`
+// promises is the parent of whatever comes in next.
+#[derive(Clone, Copy)]
+struct Parent {
+    cost: f64,
+    heuristic: f64,
+}
+
+// Numbers entries as they are queued and holds the box's tie-break rule.
+#[derive(Default)]
+struct Ties {
+    tie_break: TieBreak,
+    sequence: u64,
 }
 
-impl<S: State<Point = Diff>, D: Distance<Point = Diff>, Diff> PartialEq for AStarEntry<S, D, Diff> {
+impl<S> PartialEq for OpenEntry<S> {
     fn eq(&self, other: &Self) -> bool {
-        self.state == other.state && self.history == other.history
+        self.estimate == other.estimate
     }
 }
 
-impl<S: State<Point = Diff>, D: Distance<Point = Diff>, Diff> Eq for AStarEntry<S, D, Diff> {}
+impl<S> Eq for OpenEntry<S> {}
 
-impl<S: State<Point = Diff>, D: Distance<Point = Diff>, Diff> PartialOrd
-    for AStarEntry<S, D, Diff>
-{
+impl<S> PartialOrd for OpenEntry<S> {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl<S: State<Point = Diff>, D: Distance<Point = Diff>, Diff> Ord for AStarEntry<S, D, Diff> {
+impl<S> Ord for OpenEntry<S> {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        let a = self.score();
-        let b = other.score();
-        a.partial_cmp(&b).unwrap()
+        self.estimate.total_cmp(&other.estimate)
+    }
+}
+
+impl<S: State, H: History<S>> ScoredEntry<S, H> {
+    fn tie(&self) -> Tie {
+        Tie {
+            cost: self.cost,
+            heuristic: self.heuristic,
+            sequence: self.sequence,
+        }
+    }
+
+    fn into_parts(self) -> (S, H, f64) {
+        (self.state, self.history, self.cost)
+    }
+
+    fn parent(&self) -> Parent {
+        Parent {
+            cost: self.cost,
+            heuristic: self.heuristic,
+        }
     }
 }
 
-impl<S: State<Point = Diff>, D: Distance<Point = Diff>, Diff> ScoredEntry<S, D, Diff> {
-    fn score(&self) -> f64 {
-        self.state
-            .differences()
-            .into_iter()
-            .map(|(real, found)| D::distance(real, found))
-            .sum::<f64>()
+impl<T> Default for Queue<T> {
+    fn default() -> Self {
+        Queue {
+            entries: Vec::new(),
+        }
     }
 }
 
-impl<S: State<Point = Diff>, D: Distance<Point = Diff>, Diff> PartialEq
-    for ScoredEntry<S, D, Diff>
-{
-    fn eq(&self, other: &Self) -> bool {
-        self.state == other.state && self.history == other.history
+impl<T> Queue<T> {
+    fn push(&mut self, entry: T, order: impl Fn(&T, &T) -> Ordering) {
+        self.entries.push(entry);
+        let mut child = self.entries.len() - 1;
+        while child > 0 {
+            let parent = (child - 1) / 2;
+            if order(&self.entries[child], &self.entries[parent]) != Ordering::Less {
+                break;
+            }
+            self.entries.swap(child, parent);
+            child = parent;
+        }
+    }
+
+    fn pop(&mut self, order: impl Fn(&T, &T) -> Ordering) -> Option<T> {
+        let last = self.entries.len().checked_sub(1)?;
+        self.entries.swap(0, last);
+        let first = self.entries.pop();
+        self.sift_down(0, &order);
+        first
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Restores the heap after the order itself has changed.
+    fn reorder(&mut self, order: impl Fn(&T, &T) -> Ordering) {
+        for parent in (0..self.entries.len() / 2).rev() {
+            self.sift_down(parent, &order);
+        }
+    }
+
+    fn sift_down(&mut self, mut parent: usize, order: &impl Fn(&T, &T) -> Ordering) {
+        loop {
+            let mut first = parent;
+            for child in [2 * parent + 1, 2 * parent + 2] {
+                if child < self.entries.len()
+                    && order(&self.entries[child], &self.entries[first]) == Ordering::Less
+                {
+                    first = child;
+                }
+            }
+            if first == parent {
+                return;
+            }
+            self.entries.swap(parent, first);
+            parent = first;
+        }
     }
 }
 
-impl<S: State<Point = Diff>, D: Distance<Point = Diff>, Diff> Eq for ScoredEntry<S, D, Diff> {}
+impl Ties {
+    fn entry<S: State, H: History<S>>(
+        &mut self,
+        state: S,
+        history: H,
+        cost: f64,
+        heuristic: f64,
+        score: f64,
+    ) -> ScoredEntry<S, H> {
+        self.sequence += 1;
+        ScoredEntry {
+            state,
+            history,
+            cost,
+            heuristic,
+            score,
+            sequence: self.sequence,
+        }
+    }
 
-impl<S: State<Point = Diff>, D: Distance<Point = Diff>, Diff> PartialOrd
-    for ScoredEntry<S, D, Diff>
-{
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+    // Lower scores first, then whatever the rule prefers.
+    fn order<S: State, H: History<S>>(
+        &self,
+        a: &ScoredEntry<S, H>,
+        b: &ScoredEntry<S, H>,
+    ) -> Ordering {
+        a.score
+            .total_cmp(&b.score)
+            .then_with(|| self.tie_break.compare(&a.tie(), &b.tie()))
+    }
+
+    // Picks up the config's rule and reorders anything already queued by it.
+    fn configure<'a, S: State + 'a, H: History<S> + 'a>(
+        &mut self,
+        config: &SearchConfig,
+        queued: impl Iterator<Item = &'a mut Queue<ScoredEntry<S, H>>>,
+    ) {
+        if let Some(tie_break) = config.tie_break {
+            self.tie_break = tie_break;
+            for queue in queued {
+                queue.reorder(|a, b| self.order(a, b));
+            }
+        }
     }
 }
 
-impl<S: State<Point = Diff>, D: Distance<Point = Diff>, Diff> Ord for ScoredEntry<S, D, Diff> {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        let a = self.score();
-        let b = other.score();
-        a.partial_cmp(&b).unwrap()
+impl<S: State, H: History<S>> Default for Bucket<S, H> {
+    fn default() -> Self {
+        Bucket {
+            by_cost: Vec::new(),
+            lowest: 0,
+            len: 0,
+        }
     }
 }
 
-impl<S: State> StateBox<S> for BFSBox<S> {
-    fn init(state: S) -> Self {
+impl<S: State, H: History<S>> Bucket<S, H> {
+    fn push(&mut self, tie_break: TieBreak, entry: ScoredEntry<S, H>) {
+        let g = match tie_break {
+            TieBreak::HigherG | TieBreak::LowerG | TieBreak::LowerH => {
+                entry.cost.round().max(0f64) as usize
+            }
+            TieBreak::Fifo | TieBreak::Lifo | TieBreak::Custom(_) => 0,
+        };
+        if self.by_cost.len() <= g {
+            self.by_cost.resize_with(g + 1, VecDeque::new);
+        }
+        self.by_cost[g].push_back(entry);
+        self.lowest = self.lowest.min(g);
+        self.len += 1;
+    }
+
+    // Empty costs are trimmed from the top and skipped from the bottom as
+    // they are found, so each pop does constant work on average.
+    fn pop(&mut self, tie_break: TieBreak) -> Option<ScoredEntry<S, H>> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        match tie_break {
+            TieBreak::LowerG => {
+                while self.by_cost[self.lowest].is_empty() {
+                    self.lowest += 1;
+                }
+                self.by_cost[self.lowest].pop_front()
+            }
+            TieBreak::HigherG | TieBreak::LowerH => {
+                while self.by_cost.last().is_some_and(VecDeque::is_empty) {
+                    self.by_cost.pop();
+                }
+                self.lowest = self.lowest.min(self.by_cost.len() - 1);
+                self.by_cost.last_mut()?.pop_front()
+            }
+            TieBreak::Fifo => self.by_cost[0].pop_front(),
+            TieBreak::Lifo => self.by_cost[0].pop_back(),
+            TieBreak::Custom(_) => {
+                let entries = &mut self.by_cost[0];
+                let first = (0..entries.len())
+                    .min_by(|&a, &b| tie_break.compare(&entries[a].tie(), &entries[b].tie()))?;
+                entries.remove(first)
+            }
+        }
+    }
+
+    fn drain(&mut self) -> impl Iterator<Item = ScoredEntry<S, H>> {
+        self.len = 0;
+        self.lowest = 0;
+        mem::take(&mut self.by_cost).into_iter().flatten()
+    }
+}
+
+fn estimate<S: State, Hr: Heuristic<S>>(
+    heuristic: &Hr,
+    state: &S,
+    cost: f64,
+    parent: Option<Parent>,
+) -> f64 {
+    match parent {
+        Some(parent) => heuristic.estimate_from(state, parent.heuristic, cost - parent.cost),
+        None => heuristic.estimate(state),
+    }
+}
+
+impl<S: State, H: History<S>> StateBox<S> for BFSBox<S, H> {
+    type History = H;
+    type Heuristic = ();
+
+    fn init(state: S, _heuristic: ()) -> Self {
         let mut inner = VecDeque::new();
-        inner.push_back((state, SharedList::new()));
+        inner.push_back((state, H::default(), 0f64));
         Self { inner }
     }
 
-    fn insert(&mut self, state: S, history: SharedList<S>) {
-        self.inner.push_back((state, history));
+    fn insert(&mut self, state: S, history: H, cost: f64) {
+        self.inner.push_back((state, history, cost));
     }
 
-    fn pop(&mut self) -> Option<(S, SharedList<S>)> {
+    fn pop(&mut self) -> Option<(S, H, f64)> {
         self.inner.pop_front()
     }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
 }
 
-impl<S: State> StateBox<S> for DFSBox<S> {
-    fn init(state: S) -> Self {
-        let inner = vec![(state, SharedList::new())];
+impl<S: State, H: History<S>> StateBox<S> for DFSBox<S, H> {
+    type History = H;
+    type Heuristic = ();
+
+    fn init(state: S, _heuristic: ()) -> Self {
+        let inner = vec![(state, H::default(), 0f64)];
         Self { inner }
     }
 
-    fn insert(&mut self, state: S, history: SharedList<S>) {
-        self.inner.push((state, history));
+    fn insert(&mut self, state: S, history: H, cost: f64) {
+        self.inner.push((state, history, cost));
     }
 
-    fn pop(&mut self) -> Option<(S, SharedList<S>)> {
+    fn pop(&mut self) -> Option<(S, H, f64)> {
         self.inner.pop()
     }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
 }
 
-impl<S: State<Point = Diff>, D: Distance<Point = Diff>, Diff> StateBox<S> for AStarBox<S, D, Diff> {
-    fn init(state: S) -> Self {
-        let mut inner = BinaryHeap::new();
-        inner.push(Reverse(AStarEntry {
-            state,
-            history: SharedList::new(),
-            _dist: PhantomData,
-        }));
-        Self { inner }
+impl<S: State, H: History<S>> StateBox<S> for UniformCostBox<S, H> {
+    type History = H;
+    type Heuristic = ();
+
+    const GOAL_TEST_ON_POP: bool = true;
+
+    fn init(state: S, _heuristic: ()) -> Self {
+        let mut res = Self {
+            inner: Queue::default(),
+            ties: Ties::default(),
+        };
+        res.insert(state, H::default(), 0f64);
+        res
     }
 
-    fn insert(&mut self, state: S, history: SharedList<S>) {
-        self.inner.push(Reverse(AStarEntry {
-            state,
-            history,
-            _dist: PhantomData,
-        }))
+    fn insert(&mut self, state: S, history: H, cost: f64) {
+        let entry = self.ties.entry(state, history, cost, 0f64, cost);
+        let ties = &self.ties;
+        self.inner.push(entry, |a, b| ties.order(a, b))
+    }
+
+    fn pop(&mut self) -> Option<(S, H, f64)> {
+        let ties = &self.ties;
+        let entry = self.inner.pop(|a, b| ties.order(a, b))?;
+        Some(entry.into_parts())
     }
 
-    fn pop(&mut self) -> Option<(S, SharedList<S>)> {
-        self.inner.pop().map(|i| i.0).map(|i| (i.state, i.history))
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn configure(&mut self, config: &SearchConfig) {
+        self.ties
+            .configure(config, std::iter::once(&mut self.inner));
     }
 }
 
-impl<S: State<Point = Diff>, D: Distance<Point = Diff>, Diff> StateBox<S>
-    for StaggeredBox<S, D, Diff>
-{
-    fn init(state: S) -> Self {
-        let inner = Vec::new();
-        let mut res = Self { inner };
-        res.insert(state, SharedList::new());
+impl<S: State, Hr: Heuristic<S>, H: History<S>> StateBox<S> for AStarBox<S, Hr, H> {
+    type History = H;
+    type Heuristic = Hr;
+
+    const GOAL_TEST_ON_POP: bool = true;
+
+    fn init(state: S, heuristic: Hr) -> Self {
+        let mut res = Self {
+            inner: Queue::default(),
+            ties: Ties::default(),
+            heuristic,
+            parent: None,
+        };
+        res.insert(state, H::default(), 0f64);
         res
     }
 
-    fn insert(&mut self, state: S, history: SharedList<S>) {
+    fn insert(&mut self, state: S, history: H, cost: f64) {
+        let h = estimate(&self.heuristic, &state, cost, self.parent);
+        let entry = self.ties.entry(state, history, cost, h, cost + h);
+        let ties = &self.ties;
+        self.inner.push(entry, |a, b| ties.order(a, b))
+    }
+
+    fn pop(&mut self) -> Option<(S, H, f64)> {
+        let ties = &self.ties;
+        let entry = self.inner.pop(|a, b| ties.order(a, b))?;
+        self.parent = Some(entry.parent());
+        Some(entry.into_parts())
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn configure(&mut self, config: &SearchConfig) {
+        self.ties
+            .configure(config, std::iter::once(&mut self.inner));
+    }
+}
+
+impl<S: State, Hr: Heuristic<S>, H: History<S>> StateBox<S> for StaggeredBox<S, Hr, H> {
+    type History = H;
+    type Heuristic = Hr;
+
+    fn init(state: S, heuristic: Hr) -> Self {
+        let mut res = Self {
+            inner: Vec::new(),
+            shallowest: 0,
+            len: 0,
+            ties: Ties::default(),
+            heuristic,
+            parent: None,
+        };
+        res.insert(state, H::default(), 0f64);
+        res
+    }
+
+    fn insert(&mut self, state: S, history: H, cost: f64) {
         let required_len = history.len() + 1;
         let actual_len = self.inner.len();
         if actual_len < required_len {
             self.inner.reserve(self.inner.len() - history.len() + 1);
             while self.inner.len() < required_len {
-                self.inner.push(BinaryHeap::new());
+                self.inner.push(Queue::default());
             }
         }
-        self.inner[history.len()].push(Reverse(ScoredEntry {
-            state,
-            history,
-            _dist: PhantomData,
-        }))
+        let h = estimate(&self.heuristic, &state, cost, self.parent);
+        let depth = history.len();
+        let entry = self.ties.entry(state, history, cost, h, h);
+        let ties = &self.ties;
+        self.inner[depth].push(entry, |a, b| ties.order(a, b));
+        self.shallowest = self.shallowest.min(depth);
+        self.len += 1;
     }
 
-    fn pop(&mut self) -> Option<(S, SharedList<S>)> {
-        self.inner
-            .iter_mut()
-            .filter_map(|b| b.pop())
-            .next()
-            .map(|i| i.0)
-            .map(|i| (i.state, i.history))
+    fn pop(&mut self) -> Option<(S, H, f64)> {
+        if self.len == 0 {
+            return None;
+        }
+        while self.inner[self.shallowest].is_empty() {
+            self.shallowest += 1;
+        }
+        let ties = &self.ties;
+        let entry = self.inner[self.shallowest].pop(|a, b| ties.order(a, b))?;
+        self.len -= 1;
+        self.parent = Some(entry.parent());
+        Some(entry.into_parts())
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn configure(&mut self, config: &SearchConfig) {
+        self.ties.configure(config, self.inner.iter_mut());
+    }
+}
+
+impl<S: State, Hr: Heuristic<S>, H: History<S>> StateBox<S> for GreedyBox<S, Hr, H> {
+    type History = H;
+    type Heuristic = Hr;
+
+    fn init(state: S, heuristic: Hr) -> Self {
+        let mut res = Self {
+            inner: Queue::default(),
+            ties: Ties::default(),
+            heuristic,
+            parent: None,
+        };
+        res.insert(state, H::default(), 0f64);
+        res
+    }
+
+    fn insert(&mut self, state: S, history: H, cost: f64) {
+        let h = estimate(&self.heuristic, &state, cost, self.parent);
+        let entry = self.ties.entry(state, history, cost, h, h);
+        let ties = &self.ties;
+        self.inner.push(entry, |a, b| ties.order(a, b))
+    }
+
+    fn pop(&mut self) -> Option<(S, H, f64)> {
+        let ties = &self.ties;
+        let entry = self.inner.pop(|a, b| ties.order(a, b))?;
+        self.parent = Some(entry.parent());
+        Some(entry.into_parts())
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn configure(&mut self, config: &SearchConfig) {
+        self.ties
+            .configure(config, std::iter::once(&mut self.inner));
+    }
+}
+
+impl<S: State, Hr: Heuristic<S>, H: History<S>> StateBox<S> for WeightedAStarBox<S, Hr, H> {
+    type History = H;
+    type Heuristic = Hr;
+
+    const GOAL_TEST_ON_POP: bool = true;
+
+    fn init(state: S, heuristic: Hr) -> Self {
+        let mut res = Self {
+            weight: DEFAULT_HEURISTIC_WEIGHT,
+            inner: Queue::default(),
+            ties: Ties::default(),
+            heuristic,
+            parent: None,
+        };
+        res.insert(state, H::default(), 0f64);
+        res
+    }
+
+    fn insert(&mut self, state: S, history: H, cost: f64) {
+        let h = estimate(&self.heuristic, &state, cost, self.parent);
+        let score = cost + self.weight * h;
+        let entry = self.ties.entry(state, history, cost, h, score);
+        let ties = &self.ties;
+        self.inner.push(entry, |a, b| ties.order(a, b))
+    }
+
+    fn pop(&mut self) -> Option<(S, H, f64)> {
+        let ties = &self.ties;
+        let entry = self.inner.pop(|a, b| ties.order(a, b))?;
+        self.parent = Some(entry.parent());
+        Some(entry.into_parts())
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    // Entries keep their heuristic, so a new weight can re-score whatever is
+    // already queued without recomputing it.
+    fn configure(&mut self, config: &SearchConfig) {
+        self.ties
+            .configure(config, std::iter::once(&mut self.inner));
+        if let Some(weight) = config.heuristic_weight {
+            self.weight = weight.max(1f64);
+            for entry in &mut self.inner.entries {
+                entry.score = entry.cost + self.weight * entry.heuristic;
+            }
+            let ties = &self.ties;
+            self.inner.reorder(|a, b| ties.order(a, b));
+        }
+    }
+
+    fn suboptimality_bound(&self) -> Option<f64> {
+        Some(self.weight)
+    }
+}
+
+impl<S: State, Hr: Heuristic<S>, H: History<S>> StateBox<S> for BucketBox<S, Hr, H> {
+    type History = H;
+    type Heuristic = Hr;
+
+    const GOAL_TEST_ON_POP: bool = true;
+
+    fn init(state: S, heuristic: Hr) -> Self {
+        let mut res = Self {
+            buckets: Vec::new(),
+            lowest: 0,
+            len: 0,
+            ties: Ties::default(),
+            heuristic,
+            parent: None,
+        };
+        res.insert(state, H::default(), 0f64);
+        res
+    }
+
+    fn insert(&mut self, state: S, history: H, cost: f64) {
+        let h = estimate(&self.heuristic, &state, cost, self.parent);
+        let f = cost.round().max(0f64) + h.round().max(0f64);
+        let entry = self.ties.entry(state, history, cost, h, f);
+        self.file(entry);
+    }
+
+    fn pop(&mut self) -> Option<(S, H, f64)> {
+        if self.len == 0 {
+            return None;
+        }
+        while self.buckets[self.lowest].len == 0 {
+            self.lowest += 1;
+        }
+        self.len -= 1;
+        let entry = self.buckets[self.lowest].pop(self.ties.tie_break)?;
+        self.parent = Some(entry.parent());
+        Some(entry.into_parts())
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    // Buckets file entries by the rule, so anything already queued is filed
+    // again in the order it came in.
+    fn configure(&mut self, config: &SearchConfig) {
+        if let Some(tie_break) = config.tie_break {
+            self.ties.tie_break = tie_break;
+            let mut queued: Vec<_> = self.buckets.iter_mut().flat_map(Bucket::drain).collect();
+            queued.sort_by_key(|entry| entry.sequence);
+            self.len = 0;
+            for entry in queued {
+                self.file(entry);
+            }
+        }
+    }
+}
+
+impl<S: State, Hr: Heuristic<S>, H: History<S>> BucketBox<S, Hr, H> {
+    fn file(&mut self, entry: ScoredEntry<S, H>) {
+        let f = entry.score as usize;
+        if self.buckets.len() <= f {
+            self.buckets.resize_with(f + 1, Bucket::default);
+        }
+        self.buckets[f].push(self.ties.tie_break, entry);
+        self.lowest = self.lowest.min(f);
+        self.len += 1;
+    }
+}
+
+impl<S: State, Hr: Heuristic<S>, H: History<S>> StateBox<S> for BeamBox<S, Hr, H> {
+    type History = H;
+    type Heuristic = Hr;
+
+    fn init(state: S, heuristic: Hr) -> Self {
+        let mut res = Self {
+            width: DEFAULT_BEAM_WIDTH,
+            inner: Vec::new(),
+            draining: Vec::new(),
+            ties: Ties::default(),
+            heuristic,
+            parent: None,
+        };
+        res.insert(state, H::default(), 0f64);
+        res
+    }
+
+    fn insert(&mut self, state: S, history: H, cost: f64) {
+        while self.inner.len() <= history.len() {
+            self.inner.push(Queue::default());
+        }
+        let h = estimate(&self.heuristic, &state, cost, self.parent);
+        let depth = history.len();
+        let entry = self.ties.entry(state, history, cost, h, h);
+        let ties = &self.ties;
+        let layer = &mut self.inner[depth];
+        layer.push(entry, |a, b| ties.order(b, a));
+        if layer.len() > self.width {
+            layer.pop(|a, b| ties.order(b, a));
+        }
+    }
+
+    // Layers keep their worst entry on top so that it can be dropped, and are
+    // sorted best last once it is their turn.
+    fn pop(&mut self) -> Option<(S, H, f64)> {
+        if self.draining.is_empty() {
+            let layer = self.inner.iter_mut().find(|b| !b.is_empty())?;
+            self.draining = mem::take(layer).entries;
+            let ties = &self.ties;
+            self.draining.sort_by(|a, b| ties.order(b, a));
+        }
+        let entry = self.draining.pop()?;
+        self.parent = Some(entry.parent());
+        Some(entry.into_parts())
+    }
+
+    fn len(&self) -> usize {
+        self.draining.len() + self.inner.iter().map(|b| b.len()).sum::<usize>()
+    }
+
+    fn configure(&mut self, config: &SearchConfig) {
+        if let Some(tie_break) = config.tie_break {
+            self.ties.tie_break = tie_break;
+            let ties = &self.ties;
+            for layer in &mut self.inner {
+                layer.reorder(|a, b| ties.order(b, a));
+            }
+        }
+        if let Some(width) = config.beam_width {
+            self.width = width.max(1);
+            let ties = &self.ties;
+            for layer in &mut self.inner {
+                while layer.len() > self.width {
+                    layer.pop(|a, b| ties.order(b, a));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    pub use super::*;
+    use crate::{
+        distances::{Euclidian, Manhattan},
+        heuristics::PathMax,
+        machine::Machine,
+        testing::{board, boards, is_valid_path, Junction},
+        tileboard::TileBoard,
+        traits::Solver,
+    };
+
+    #[test]
+    fn uniform_cost_finds_the_cheapest_path_alright() {
+        let machine = Machine::new(Junction(0), Junction(3));
+
+        let (fewest_moves, _) = Solver::<_>::find_solution::<BFSBox<_>>(machine.clone()).unwrap();
+        assert_eq!(fewest_moves.path, vec![Junction(0), Junction(3)]);
+        assert_eq!(fewest_moves.cost, 10f64);
+
+        let (cheapest, _) = Solver::<_>::find_solution::<UniformCostBox<_>>(machine).unwrap();
+        assert_eq!(
+            cheapest.path,
+            vec![Junction(0), Junction(1), Junction(2), Junction(3)]
+        );
+        assert_eq!(cheapest.cost, 3f64);
+    }
+
+    #[test]
+    fn beam_keeps_its_width_alright() {
+        let init: TileBoard<3, 3> = board(20);
+        let config = SearchConfig {
+            beam_width: Some(50),
+            ..SearchConfig::default()
+        };
+        let machine = Machine::new(init.clone(), TileBoard::default()).with_config(config);
+
+        let report = Solver::<_>::search::<BeamBox<_, Manhattan>>(machine);
+        assert!(report.stats.peak_frontier <= 100);
+
+        let (solution, _) = report.solution().unwrap();
+        assert_eq!(solution.path.first(), Some(&init));
+        assert_eq!(solution.path.last(), Some(&TileBoard::default()));
+        assert!(is_valid_path(&solution.path));
+    }
+
+    #[test]
+    fn weighted_astar_stays_within_its_bound_alright() {
+        for init in boards::<3, 3>(5, 40) {
+            let config = SearchConfig {
+                heuristic_weight: Some(1.5),
+                ..SearchConfig::default()
+            };
+            let machine = Machine::new(init, TileBoard::default());
+            let (cheapest, _) =
+                Solver::<_>::find_solution::<UniformCostBox<_>>(machine.clone()).unwrap();
+
+            let report = Solver::<_>::search::<WeightedAStarBox<_, Euclidian>>(
+                machine.clone().with_config(config),
+            );
+            assert_eq!(report.stats.suboptimality_bound, Some(1.5));
+            let (weighted, _) = report.solution().unwrap();
+            assert!(weighted.cost <= cheapest.cost * 1.5);
+
+            let (greedy, _) =
+                Solver::<_>::find_solution::<GreedyBox<_, Manhattan>>(machine).unwrap();
+            assert_eq!(greedy.path.last(), Some(&TileBoard::default()));
+            assert!(greedy.cost >= cheapest.cost);
+        }
+    }
+
+    #[test]
+    fn orders_nan_scores_alright() {
+        let mut ties = Ties::default();
+        let mut queue = Queue::default();
+        for score in [1f64, f64::NAN, 0f64] {
+            let entry = ties.entry(
+                Junction(0),
+                SharedList::<Junction>::new(),
+                0f64,
+                score,
+                score,
+            );
+            queue.push(entry, |a, b| ties.order(a, b));
+        }
+
+        let mut pop = || queue.pop(|a, b| ties.order(a, b)).unwrap().score;
+        assert_eq!(pop(), 0f64);
+        assert_eq!(pop(), 1f64);
+        assert!(pop().is_nan());
+    }
+
+    // Drops to nothing one cheap step away from the start.
+    #[derive(Default)]
+    struct Dip;
+
+    impl Heuristic<Junction> for Dip {
+        fn estimate(&self, state: &Junction) -> f64 {
+            match state.0 {
+                1 => 0f64,
+                n => 3f64 - n as f64,
+            }
+        }
+    }
+
+    #[test]
+    fn passes_parent_estimates_on_alright() {
+        let mut astar = AStarBox::<_, _>::init(Junction(0), PathMax(Dip));
+        let (state, history, cost) = astar.pop().unwrap();
+        astar.insert(Junction(1), history.push(state), cost + 1f64);
+        assert_eq!(astar.inner.entries.first().map(|i| i.heuristic), Some(2f64));
+
+        let mut staggered = StaggeredBox::<_, _>::init(Junction(0), PathMax(Dip));
+        let (state, history, cost) = staggered.pop().unwrap();
+        staggered.insert(Junction(1), history.push(state), cost + 1f64);
+        assert_eq!(
+            staggered.inner[1].entries.first().map(|i| i.heuristic),
+            Some(2f64)
+        );
+
+        let mut greedy = GreedyBox::<_, _>::init(Junction(0), PathMax(Dip));
+        let (state, history, cost) = greedy.pop().unwrap();
+        greedy.insert(Junction(1), history.push(state), cost + 1f64);
+        assert_eq!(
+            greedy.inner.entries.first().map(|i| i.heuristic),
+            Some(2f64)
+        );
+
+        let machine = Machine::new(Junction(0), Junction(3));
+        let (solution, _) =
+            Solver::<_>::find_solution::<AStarBox<_, PathMax<Dip>>>(machine).unwrap();
+        assert_eq!(solution.cost, 3f64);
+    }
+
+    // Always zero, which turns `BucketBox` into a bucketed uniform-cost search.
+    #[derive(Default)]
+    struct Zero;
+
+    impl<S: State> Heuristic<S> for Zero {
+        fn estimate(&self, _state: &S) -> f64 {
+            0f64
+        }
+    }
+
+    #[test]
+    fn buckets_match_uniform_cost_alright() {
+        for tie_break in [TieBreak::HigherG, TieBreak::LowerG] {
+            let init: TileBoard<3, 3> = board(30);
+            let config = SearchConfig {
+                tie_break: Some(tie_break),
+                ..SearchConfig::default()
+            };
+            let machine = Machine::new(init.clone(), TileBoard::default()).with_config(config);
+            let (cheapest, _) =
+                Solver::<_>::find_solution::<UniformCostBox<_>>(machine.clone()).unwrap();
+
+            let (bucketed, _) =
+                Solver::<_>::find_solution::<BucketBox<_, Zero>>(machine.clone()).unwrap();
+            assert_eq!(bucketed.cost, cheapest.cost);
+
+            let (informed, _) =
+                Solver::<_>::find_solution::<BucketBox<_, Manhattan>>(machine).unwrap();
+            assert_eq!(informed.path.first(), Some(&init));
+            assert_eq!(informed.path.last(), Some(&TileBoard::default()));
+            assert!(is_valid_path(&informed.path));
+        }
+    }
+
+    fn higher_cost(a: &Tie, b: &Tie) -> std::cmp::Ordering {
+        b.cost.total_cmp(&a.cost)
+    }
+
+    #[test]
+    fn breaks_ties_deterministically_alright() {
+        let init: TileBoard<3, 3> = board(40);
+        let run = |tie_break| {
+            let config = SearchConfig {
+                tie_break: Some(tie_break),
+                ..SearchConfig::default()
+            };
+            let machine = Machine::new(init.clone(), TileBoard::default()).with_config(config);
+            Solver::<_>::search::<AStarBox<_, Manhattan>>(machine)
+        };
+
+        for tie_break in [
+            TieBreak::HigherG,
+            TieBreak::LowerG,
+            TieBreak::LowerH,
+            TieBreak::Fifo,
+            TieBreak::Lifo,
+        ] {
+            let (first, second) = (run(tie_break), run(tie_break));
+            assert_eq!(first.outcome, second.outcome);
+            assert_eq!(first.stats.expanded, second.stats.expanded);
+        }
+
+        let custom = run(TieBreak::Custom(higher_cost));
+        let built_in = run(TieBreak::HigherG);
+        assert_eq!(custom.outcome, built_in.outcome);
+        assert_eq!(custom.stats.expanded, built_in.stats.expanded);
+    }
+
+    // Estimates whatever number the junction carries, so that a junction
+    // queued with cost `10 - n` always has f = 10.
+    struct Carried;
+
+    impl Heuristic<Junction> for Carried {
+        fn estimate(&self, state: &Junction) -> f64 {
+            state.0 as f64
+        }
+    }
+
+    // Queues three entries with the same f, with g of 4, 6 and 2 in that
+    // order, and returns the g of each as it pops.
+    fn pop_order<SB: StateBox<Junction, Heuristic = Carried>>(tie_break: TieBreak) -> Vec<f64> {
+        let mut queue = SB::init(Junction(0), Carried);
+        queue.configure(&SearchConfig {
+            tie_break: Some(tie_break),
+            ..SearchConfig::default()
+        });
+        let (state, history, _) = queue.pop().unwrap();
+        for g in [4, 6, 2] {
+            queue.insert(Junction(10 - g), history.push(state.clone()), g as f64);
+        }
+        std::iter::from_fn(|| queue.pop().map(|(_, _, cost)| cost)).collect()
+    }
+
+    // Queues junctions 1, 2 and 3 at the same cost, which only the order they
+    // came in can separate, and returns each as it pops.
+    fn uniform_cost_order(tie_break: TieBreak) -> Vec<usize> {
+        let mut queue = UniformCostBox::<_>::init(Junction(0), ());
+        queue.configure(&SearchConfig {
+            tie_break: Some(tie_break),
+            ..SearchConfig::default()
+        });
+        let (state, history, _) = queue.pop().unwrap();
+        for n in [1, 2, 3] {
+            queue.insert(Junction(n), history.push(state.clone()), 1f64);
+        }
+        std::iter::from_fn(|| queue.pop().map(|(state, _, _)| state.0)).collect()
+    }
+
+    fn lower_cost(a: &Tie, b: &Tie) -> std::cmp::Ordering {
+        a.cost.total_cmp(&b.cost)
+    }
+
+    fn later_first(a: &Tie, b: &Tie) -> std::cmp::Ordering {
+        b.sequence.cmp(&a.sequence)
+    }
+
+    #[test]
+    fn pops_ties_by_the_rule_alright() {
+        for order in [pop_order::<AStarBox<_, _>>, pop_order::<BucketBox<_, _>>] {
+            assert_eq!(order(TieBreak::HigherG), vec![6f64, 4f64, 2f64]);
+            assert_eq!(order(TieBreak::LowerG), vec![2f64, 4f64, 6f64]);
+            assert_eq!(order(TieBreak::LowerH), vec![6f64, 4f64, 2f64]);
+            assert_eq!(order(TieBreak::Fifo), vec![4f64, 6f64, 2f64]);
+            assert_eq!(order(TieBreak::Lifo), vec![2f64, 6f64, 4f64]);
+            assert_eq!(order(TieBreak::Custom(lower_cost)), vec![2f64, 4f64, 6f64]);
+            assert_eq!(order(TieBreak::Custom(higher_cost)), vec![6f64, 4f64, 2f64]);
+        }
+
+        let order = uniform_cost_order;
+        assert_eq!(order(TieBreak::HigherG), vec![1, 2, 3]);
+        assert_eq!(order(TieBreak::Fifo), vec![1, 2, 3]);
+        assert_eq!(order(TieBreak::Lifo), vec![3, 2, 1]);
+        assert_eq!(order(TieBreak::Custom(later_first)), vec![3, 2, 1]);
     }
 }
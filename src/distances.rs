@@ -1,9 +1,9 @@
 use crate::{Coord, traits::Distance};
 
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Manhattan;
 
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Euclidian;
 
 impl Distance for Manhattan {
@@ -12,7 +12,7 @@ impl Distance for Manhattan {
     fn distance(a: Self::Point, b: Self::Point) -> f64 {
         let col_diff = (a.column() as isize - b.column() as isize).abs() as f64;
         let row_diff = (a.row() as isize - b.row() as isize).abs() as f64;
-        (col_diff + row_diff) as f64
+        col_diff + row_diff
     }
 }
 
@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+
+use crate::traits::{Goal, State};
+
+#[derive(Clone)]
+pub struct Exact<S: State>(pub S);
+
+#[derive(Clone)]
+pub struct AnyOf<S: State>(pub HashSet<S>);
+
+#[derive(Clone)]
+pub struct Predicate<F>(pub F);
+
+impl<S: State> Goal<S> for Exact<S> {
+    fn is_complete(&self, state: &S) -> bool {
+        self.0 == *state
+    }
+}
+
+impl<S: State> Goal<S> for AnyOf<S> {
+    fn is_complete(&self, state: &S) -> bool {
+        self.0.contains(state)
+    }
+}
+
+impl<S: State, F: Fn(&S) -> bool> Goal<S> for Predicate<F> {
+    fn is_complete(&self, state: &S) -> bool {
+        (self.0)(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    pub use super::*;
+    use crate::{
+        boxes::BFSBox, hanoi::Hanoi, machine::Machine, testing::board, tileboard::TileBoard,
+        traits::Solver,
+    };
+
+    #[test]
+    fn solves_for_a_predicate_alright() {
+        let first_row_solved = |board: &TileBoard<3, 3>| {
+            board
+                .differences()
+                .into_iter()
+                .all(|(real, found)| real.row() != 0 || real == found)
+        };
+        let machine = Machine::with_goal(board(30), Predicate(first_row_solved));
+        let path = Solver::<_>::find_one::<BFSBox<_>>(machine).unwrap();
+
+        assert!(first_row_solved(path.last().unwrap()));
+    }
+
+    #[test]
+    fn solves_for_any_peg_alright() {
+        let off_the_left_peg = |hanoi: &Hanoi<4>| {
+            let differences = hanoi.differences();
+            let peg = differences[0].1.column();
+            peg != 0 && differences.iter().all(|(_, found)| found.column() == peg)
+        };
+        let machine = Machine::with_goal(Hanoi::new(), Predicate(off_the_left_peg));
+        let any_peg = Solver::<_>::find_one::<BFSBox<_>>(machine).unwrap();
+        let right_peg =
+            Solver::<_>::find_one::<BFSBox<_>>(Machine::new(Hanoi::<4>::new(), Hanoi::solved()))
+                .unwrap();
+
+        assert!(off_the_left_peg(any_peg.last().unwrap()));
+        assert_eq!(any_peg.len(), right_peg.len());
+    }
+
+    #[test]
+    fn solves_for_a_set_of_states_alright() {
+        let goals: HashSet<_> = Hanoi::<3>::solved().next().into_iter().collect();
+        let machine = Machine::with_goal(Hanoi::new(), AnyOf(goals.clone()));
+        let path = Solver::<_>::find_one::<BFSBox<_>>(machine).unwrap();
+
+        assert!(goals.contains(path.last().unwrap()));
+    }
+}
@@ -0,0 +1,324 @@
+use std::{
+    cmp::Reverse,
+    collections::{hash_map::DefaultHasher, BinaryHeap, HashMap},
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    boxes::OpenEntry,
+    search::{SearchOutcome, SearchReport, SearchStats, Solution},
+    traits::{Heuristic, Observer, Solver, State, StateBox, Supply},
+};
+
+#[derive(Clone)]
+pub struct HDAStar<S: State, Hr: Heuristic<S>> {
+    init_state: S,
+    complete_state: S,
+    heuristic: Hr,
+    threads: usize,
+}
+
+struct Work<S> {
+    state: S,
+    parent: Option<S>,
+    cost: f64,
+    depth: usize,
+}
+
+// Shared by every worker. `bound` mirrors the incumbent's cost so it can be
+// read without locking. `outstanding` counts messages in flight plus workers
+// that still hold useful open states, so reaching zero means the search is
+// over: nobody has work left and nothing more can arrive.
+struct Shared<S> {
+    incumbent: Mutex<Option<(f64, S)>>,
+    bound: AtomicU64,
+    outstanding: AtomicUsize,
+}
+
+// Each state's cheapest known cost, with the parent and depth it was reached
+// at along that path.
+type Closed<S> = HashMap<S, (f64, Option<S>, usize)>;
+
+struct Worker<'h, S: State, Hr: Heuristic<S>> {
+    id: usize,
+    complete_state: S,
+    heuristic: &'h Hr,
+    inbox: Receiver<Work<S>>,
+    outboxes: Vec<Sender<Work<S>>>,
+    open: BinaryHeap<Reverse<OpenEntry<S>>>,
+    closed: Closed<S>,
+    busy: bool,
+    stats: SearchStats,
+}
+
+impl<S: State, Hr: Heuristic<S> + Default> HDAStar<S, Hr> {
+    pub fn new(init_state: S, complete_state: S) -> Self {
+        HDAStar::with_heuristic(init_state, complete_state, Hr::default())
+    }
+}
+
+impl<S: State, Hr: Heuristic<S>> HDAStar<S, Hr> {
+    pub fn with_heuristic(init_state: S, complete_state: S, heuristic: Hr) -> Self {
+        HDAStar {
+            init_state,
+            complete_state,
+            heuristic,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+}
+
+fn owner<S: Hash>(state: &S, threads: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    state.hash(&mut hasher);
+    (hasher.finish() % threads as u64) as usize
+}
+
+impl<S: State, Hr: Heuristic<S>> Worker<'_, S, Hr> {
+    fn receive(&mut self, work: Work<S>, shared: &Shared<S>) {
+        if !self.busy {
+            self.busy = true;
+            shared.outstanding.fetch_add(1, Ordering::SeqCst);
+        }
+        self.accept(work);
+        shared.outstanding.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    // Keeps a state only if it is new or reached more cheaply than before, in
+    // which case it is re-opened with the better parent.
+    fn accept(&mut self, work: Work<S>) {
+        if let Some((best, _, _)) = self.closed.get(&work.state) {
+            if *best <= work.cost {
+                self.stats.duplicates += 1;
+                return;
+            }
+        }
+
+        self.closed
+            .insert(work.state.clone(), (work.cost, work.parent, work.depth));
+        self.open.push(Reverse(OpenEntry {
+            estimate: work.cost + self.heuristic.estimate(&work.state),
+            cost: work.cost,
+            state: work.state,
+        }));
+        self.stats.peak_frontier = self.stats.peak_frontier.max(self.open.len());
+    }
+
+    fn next_open(&mut self, bound: f64) -> Option<OpenEntry<S>> {
+        while let Some(Reverse(entry)) = self.open.pop() {
+            if entry.estimate >= bound {
+                self.open.clear();
+                return None;
+            }
+            if self.closed[&entry.state].0 < entry.cost {
+                continue;
+            }
+            return Some(entry);
+        }
+        None
+    }
+
+    fn expand(&mut self, entry: OpenEntry<S>, shared: &Shared<S>) {
+        if entry.state == self.complete_state {
+            let mut incumbent = shared.incumbent.lock().unwrap();
+            if entry.cost < f64::from_bits(shared.bound.load(Ordering::SeqCst)) {
+                shared.bound.store(entry.cost.to_bits(), Ordering::SeqCst);
+                *incumbent = Some((entry.cost, entry.state));
+            }
+            return;
+        }
+
+        let depth = self.closed[&entry.state].2;
+        self.stats.expanded += 1;
+        self.stats.max_depth = self.stats.max_depth.max(depth + 1);
+        for (next_state, step_cost) in entry.state.next_weighted() {
+            self.stats.generated += 1;
+            let work = Work {
+                parent: Some(entry.state.clone()),
+                cost: entry.cost + step_cost,
+                depth: depth + 1,
+                state: next_state,
+            };
+            let destination = owner(&work.state, self.outboxes.len());
+            if destination == self.id {
+                self.accept(work);
+            } else {
+                shared.outstanding.fetch_add(1, Ordering::SeqCst);
+                self.outboxes[destination].send(work).unwrap();
+            }
+        }
+    }
+
+    fn run(mut self, shared: &Shared<S>) -> (Closed<S>, SearchStats) {
+        loop {
+            while let Ok(work) = self.inbox.try_recv() {
+                self.receive(work, shared);
+            }
+
+            if let Some(entry) = self.next_open(f64::from_bits(shared.bound.load(Ordering::SeqCst)))
+            {
+                self.expand(entry, shared);
+                continue;
+            }
+
+            if self.busy {
+                self.busy = false;
+                shared.outstanding.fetch_sub(1, Ordering::SeqCst);
+            }
+            if shared.outstanding.load(Ordering::SeqCst) == 0 {
+                break;
+            }
+            match self.inbox.recv_timeout(Duration::from_millis(1)) {
+                Ok(work) => self.receive(work, shared),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        self.stats.peak_seen = self.closed.len();
+        (self.closed, self.stats)
+    }
+}
+
+// Every worker owns the states that hash to it, with its own open and closed
+// sets, and forwards successors to their owners. Workers keep expanding until
+// no open state anywhere can beat the cheapest goal found so far. Expansions
+// happen on worker threads, so observers only hear about the goal and the end
+// of the search.
+impl<S, Hr> Solver<S> for HDAStar<S, Hr>
+where
+    S: State + Send,
+    Hr: Heuristic<S> + Sync,
+{
+    type Supplier = ();
+
+    fn search_observed<SB: StateBox<S>, O: Observer<S>>(self, observer: &mut O) -> SearchReport<S>
+    where
+        (): Supply<SB::Heuristic>,
+    {
+        let started = Instant::now();
+        let shared = Shared {
+            incumbent: Mutex::new(None),
+            bound: AtomicU64::new(f64::INFINITY.to_bits()),
+            outstanding: AtomicUsize::new(1),
+        };
+
+        let (outboxes, inboxes): (Vec<_>, Vec<_>) =
+            (0..self.threads).map(|_| mpsc::channel()).unzip();
+        outboxes[owner(&self.init_state, self.threads)]
+            .send(Work {
+                state: self.init_state,
+                parent: None,
+                cost: 0f64,
+                depth: 0,
+            })
+            .unwrap();
+
+        let complete_state = self.complete_state;
+        let heuristic = &self.heuristic;
+        let results: Vec<_> = thread::scope(|scope| {
+            let handles: Vec<_> = inboxes
+                .into_iter()
+                .enumerate()
+                .map(|(id, inbox)| {
+                    let worker = Worker {
+                        id,
+                        complete_state: complete_state.clone(),
+                        heuristic,
+                        inbox,
+                        outboxes: outboxes.clone(),
+                        open: BinaryHeap::new(),
+                        closed: HashMap::new(),
+                        busy: false,
+                        stats: SearchStats::default(),
+                    };
+                    let shared = &shared;
+                    scope.spawn(move || worker.run(shared))
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        let mut stats = SearchStats::default();
+        let mut closed = HashMap::new();
+        for (worker_closed, worker_stats) in results {
+            stats.expanded += worker_stats.expanded;
+            stats.generated += worker_stats.generated;
+            stats.duplicates += worker_stats.duplicates;
+            stats.peak_frontier += worker_stats.peak_frontier;
+            stats.peak_seen += worker_stats.peak_seen;
+            stats.max_depth = stats.max_depth.max(worker_stats.max_depth);
+            closed.extend(worker_closed);
+        }
+
+        let outcome = match shared.incumbent.into_inner().unwrap() {
+            Some((cost, goal)) => {
+                let mut path = vec![goal];
+                while let Some((_, Some(parent), _)) = closed.get(path.last().unwrap()) {
+                    path.push(parent.clone());
+                }
+                path.reverse();
+                SearchOutcome::Found(Solution { path, cost })
+            }
+            None => SearchOutcome::Exhausted,
+        };
+        SearchReport::new(outcome, stats, started, observer)
+    }
+
+    // Only the optimal solution is ever kept, so that is all this returns.
+    fn find_all_observed<SB: StateBox<S>, O: Observer<S>>(self, observer: &mut O) -> Vec<Vec<S>>
+    where
+        (): Supply<SB::Heuristic>,
+    {
+        match self.search_observed::<SB, _>(observer).outcome {
+            SearchOutcome::Found(solution) => vec![solution.path],
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    pub use super::*;
+    use crate::{
+        boxes::{AStarBox, UniformCostBox},
+        distances::Euclidian,
+        machine::Machine,
+        testing::{boards, is_valid_path},
+        tileboard::TileBoard,
+    };
+
+    #[test]
+    fn matches_astar_costs_alright() {
+        for init in boards::<3, 3>(5, 40) {
+            let machine = Machine::new(init.clone(), TileBoard::default());
+            let (expected, _) =
+                Solver::<_>::find_solution::<AStarBox<_, Euclidian>>(machine.clone()).unwrap();
+            let (cheapest, _) = Solver::<_>::find_solution::<UniformCostBox<_>>(machine).unwrap();
+
+            let solver =
+                HDAStar::<_, Euclidian>::new(init.clone(), TileBoard::default()).with_threads(4);
+            let (solution, _) =
+                Solver::<_>::find_solution::<AStarBox<_, Euclidian>>(solver).unwrap();
+
+            assert_eq!(solution.cost, expected.cost);
+            assert_eq!(solution.cost, cheapest.cost);
+            assert_eq!(solution.path.len() as f64, solution.cost + 1f64);
+            assert_eq!(solution.path.first(), Some(&init));
+            assert_eq!(solution.path.last(), Some(&TileBoard::default()));
+            assert!(is_valid_path(&solution.path));
+        }
+    }
+}
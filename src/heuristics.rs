@@ -0,0 +1,301 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+use crate::{
+    tileboard::TileBoard,
+    traits::{Heuristic, State},
+};
+
+// Manhattan distance over every tile but the blank, plus two moves for each
+// tile that has to step out of its goal row or column to let another past.
+// The fewest tiles a line needs to move aside is its length less the longest
+// run of its tiles that are already in goal order.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LinearConflict;
+
+impl<const C: usize, const R: usize> Heuristic<TileBoard<C, R>> for LinearConflict {
+    fn estimate(&self, board: &TileBoard<C, R>) -> f64 {
+        let mut total = 0;
+        let mut line = Vec::with_capacity(C.max(R));
+
+        for row in 0..R {
+            line.clear();
+            for column in 0..C {
+                let tile = board.tile(column, row);
+                if tile == 0 {
+                    continue;
+                }
+                let (goal_column, goal_row) = ((tile - 1) % C, (tile - 1) / C);
+                total += goal_column.abs_diff(column) + goal_row.abs_diff(row);
+                if goal_row == row {
+                    line.push(goal_column);
+                }
+            }
+            total += 2 * (line.len() - longest_increasing(&line));
+        }
+
+        for column in 0..C {
+            line.clear();
+            for row in 0..R {
+                let tile = board.tile(column, row);
+                if tile != 0 && (tile - 1) % C == column {
+                    line.push((tile - 1) / C);
+                }
+            }
+            total += 2 * (line.len() - longest_increasing(&line));
+        }
+
+        total as f64
+    }
+}
+
+// Tiles change rows only on vertical moves and columns only on horizontal
+// ones, so the fewest moves that get every row holding the right tiles adds
+// to the fewest for the columns. Either count only looks at how many tiles
+// bound for each line every line holds, which keeps the tables small.
+#[derive(Clone)]
+pub struct WalkingDistance<const C: usize, const R: usize> {
+    rows: Walks,
+    columns: Walks,
+}
+
+// Fewest moves to the goal keyed by how many tiles bound for each line every
+// line holds, line by line, followed by the line holding the blank.
+type Walks = Arc<HashMap<Vec<u8>, u8>>;
+
+// Rows of one board are the columns of its transpose, so square boards
+// build one table and share it between the two directions.
+impl<const C: usize, const R: usize> Default for WalkingDistance<C, R> {
+    fn default() -> Self {
+        let rows = walks(R, C);
+        let columns = if C == R { rows.clone() } else { walks(C, R) };
+        WalkingDistance { rows, columns }
+    }
+}
+
+impl<const C: usize, const R: usize> Heuristic<TileBoard<C, R>> for WalkingDistance<C, R> {
+    fn estimate(&self, board: &TileBoard<C, R>) -> f64 {
+        let mut rows = vec![0; R * R + 1];
+        let mut columns = vec![0; C * C + 1];
+        for row in 0..R {
+            for column in 0..C {
+                let tile = board.tile(column, row);
+                if tile == 0 {
+                    rows[R * R] = row as u8;
+                    columns[C * C] = column as u8;
+                } else {
+                    rows[row * R + (tile - 1) / C] += 1;
+                    columns[column * C + (tile - 1) % C] += 1;
+                }
+            }
+        }
+
+        [(&self.rows, rows), (&self.columns, columns)]
+            .iter()
+            .map(|(walks, key)| walks.get(key).map_or(0f64, |&moves| moves as f64))
+            .sum()
+    }
+}
+
+// A breadth-first walk out from the goal, where each move swaps the blank
+// with a tile from a neighbouring line.
+fn walks(lines: usize, width: usize) -> Walks {
+    let mut goal = vec![0; lines * lines + 1];
+    for line in 0..lines {
+        goal[line * lines + line] = width as u8;
+    }
+    goal[lines * lines - 1] -= 1;
+    goal[lines * lines] = (lines - 1) as u8;
+
+    let mut walks = HashMap::new();
+    walks.insert(goal.clone(), 0);
+    let mut queue = VecDeque::from([goal]);
+    while let Some(key) = queue.pop_front() {
+        let moves = walks[&key] + 1;
+        let blank = key[lines * lines] as usize;
+        for line in [blank.wrapping_sub(1), blank + 1] {
+            if line >= lines {
+                continue;
+            }
+            for bound in 0..lines {
+                if key[line * lines + bound] == 0 {
+                    continue;
+                }
+                let mut next = key.clone();
+                next[line * lines + bound] -= 1;
+                next[blank * lines + bound] += 1;
+                next[lines * lines] = line as u8;
+                if !walks.contains_key(&next) {
+                    walks.insert(next.clone(), moves);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    Arc::new(walks)
+}
+
+// The larger of two estimates, which stays admissible when both are. Nest
+// them to take the largest of more.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Max<A, B>(pub A, pub B);
+
+// Two estimates added together. That is only admissible when they count
+// costs that never overlap, or once each is scaled so the weights sum to one.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sum<A, B>(pub A, pub B);
+
+// An estimate times a factor, such as the weights of a weighted sum.
+#[derive(Clone, Copy, Debug)]
+pub struct Scaled<H>(pub H, pub f64);
+
+// Never lets an estimate fall by more than the cost of the step taken from
+// the parent, so f never drops along a path even when the heuristic is
+// inconsistent. `Max` passes the parent's estimate on to both sides, but the
+// parent's share of a sum or scaled estimate isn't known, so it has no effect
+// inside `Sum` or `Scaled`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PathMax<H>(pub H);
+
+impl<S: State, A: Heuristic<S>, B: Heuristic<S>> Heuristic<S> for Max<A, B> {
+    fn estimate(&self, state: &S) -> f64 {
+        self.0.estimate(state).max(self.1.estimate(state))
+    }
+
+    fn estimate_from(&self, state: &S, parent_estimate: f64, step_cost: f64) -> f64 {
+        let a = self.0.estimate_from(state, parent_estimate, step_cost);
+        a.max(self.1.estimate_from(state, parent_estimate, step_cost))
+    }
+}
+
+impl<S: State, A: Heuristic<S>, B: Heuristic<S>> Heuristic<S> for Sum<A, B> {
+    fn estimate(&self, state: &S) -> f64 {
+        self.0.estimate(state) + self.1.estimate(state)
+    }
+}
+
+impl<S: State, H: Heuristic<S>> Heuristic<S> for Scaled<H> {
+    fn estimate(&self, state: &S) -> f64 {
+        self.0.estimate(state) * self.1
+    }
+}
+
+impl<S: State, H: Heuristic<S>> Heuristic<S> for PathMax<H> {
+    fn estimate(&self, state: &S) -> f64 {
+        self.0.estimate(state)
+    }
+
+    fn estimate_from(&self, state: &S, parent_estimate: f64, step_cost: f64) -> f64 {
+        self.0
+            .estimate_from(state, parent_estimate, step_cost)
+            .max(parent_estimate - step_cost)
+    }
+}
+
+fn longest_increasing(values: &[usize]) -> usize {
+    let mut tails: Vec<usize> = Vec::with_capacity(values.len());
+    for &value in values {
+        match tails.binary_search(&value) {
+            Ok(_) => {}
+            Err(i) if i == tails.len() => tails.push(value),
+            Err(i) => tails[i] = value,
+        }
+    }
+    tails.len()
+}
+
+#[cfg(test)]
+mod tests {
+    pub use super::*;
+    use crate::{
+        boxes::{AStarBox, StaggeredBox, UniformCostBox},
+        distances::{Euclidian, Manhattan},
+        machine::Machine,
+        testing::boards,
+        traits::Solver,
+    };
+    use std::collections::{HashMap, VecDeque};
+
+    // Moves are reversible, so a breadth-first walk out from the goal gives
+    // every reachable board's exact distance to it.
+    fn distances<const C: usize, const R: usize>() -> HashMap<TileBoard<C, R>, usize> {
+        let mut distances = HashMap::new();
+        distances.insert(TileBoard::default(), 0);
+        let mut queue = VecDeque::from([TileBoard::default()]);
+        while let Some(board) = queue.pop_front() {
+            let distance = distances[&board];
+            for next in board.next() {
+                if !distances.contains_key(&next) {
+                    distances.insert(next.clone(), distance + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        distances
+    }
+
+    fn assert_admissible<const C: usize, const R: usize, Hr: Heuristic<TileBoard<C, R>>>(
+        heuristic: Hr,
+    ) {
+        let distances = distances::<C, R>();
+        assert_eq!(distances.len(), (1..=C * R).product::<usize>() / 2);
+        for (board, distance) in distances {
+            let estimate = heuristic.estimate(&board);
+            assert!(estimate <= distance as f64, "{:?}", board);
+        }
+    }
+
+    #[test]
+    fn never_overestimates_alright() {
+        assert_admissible::<3, 2, _>(LinearConflict);
+        assert_admissible::<3, 3, _>(LinearConflict);
+    }
+
+    #[test]
+    fn combines_estimates_alright() {
+        let strongest = PathMax(Max(LinearConflict, WalkingDistance::<3, 3>::default()));
+        for init in boards::<3, 3>(10, 40) {
+            let (manhattan, conflict) = (Manhattan.estimate(&init), LinearConflict.estimate(&init));
+            let halves = Sum(Scaled(Manhattan, 0.5), Scaled(Euclidian, 0.5));
+            assert_eq!(
+                Max(Manhattan, LinearConflict).estimate(&init),
+                manhattan.max(conflict)
+            );
+            assert_eq!(
+                halves.estimate(&init),
+                (manhattan + Euclidian.estimate(&init)) / 2f64
+            );
+            assert_eq!(
+                PathMax(LinearConflict).estimate_from(&init, conflict + 3f64, 1f64),
+                conflict + 2f64
+            );
+
+            let machine = Machine::new(init, TileBoard::default());
+            let (cheapest, _) =
+                Solver::<_>::find_solution::<UniformCostBox<_>>(machine.clone()).unwrap();
+            let (informed, _) = Solver::<_>::find_solution::<AStarBox<_, _>>(
+                machine.clone().with_heuristic(strongest.clone()),
+            )
+            .unwrap();
+            assert_eq!(informed.cost, cheapest.cost);
+
+            let (staggered, _) = Solver::<_>::find_solution::<StaggeredBox<_, _>>(
+                machine.with_heuristic(Scaled(Manhattan, 1.5)),
+            )
+            .unwrap();
+            assert_eq!(staggered.path.last(), Some(&TileBoard::default()));
+        }
+    }
+
+    #[test]
+    fn walks_any_board_alright() {
+        assert_admissible::<3, 2, _>(WalkingDistance::default());
+        assert_admissible::<2, 4, _>(WalkingDistance::default());
+        assert_admissible::<3, 3, _>(WalkingDistance::default());
+
+        let walking = WalkingDistance::<3, 3>::default();
+        assert_eq!(walking.estimate(&TileBoard::default()), 0f64);
+    }
+}
@@ -0,0 +1,224 @@
+use std::time::Instant;
+
+use crate::{
+    search::{SearchOutcome, SearchReport, SearchStats, Solution},
+    traits::{Heuristic, Observer, Solver, State, StateBox, Supply},
+};
+
+#[derive(Clone)]
+pub struct IDAStar<S: State, Hr: Heuristic<S>> {
+    init_state: S,
+    complete_state: S,
+    heuristic: Hr,
+}
+
+struct Progress<'o, S, O> {
+    stats: SearchStats,
+    results: Vec<Solution<S>>,
+    find_all: bool,
+    observer: &'o mut O,
+}
+
+enum Probe {
+    Found,
+    Exceeded(f64),
+    Exhausted,
+}
+
+impl<S: State, Hr: Heuristic<S> + Default> IDAStar<S, Hr> {
+    pub fn new(init_state: S, complete_state: S) -> Self {
+        IDAStar::with_heuristic(init_state, complete_state, Hr::default())
+    }
+}
+
+impl<S: State, Hr: Heuristic<S>> IDAStar<S, Hr> {
+    pub fn with_heuristic(init_state: S, complete_state: S, heuristic: Hr) -> Self {
+        IDAStar {
+            init_state,
+            complete_state,
+            heuristic,
+        }
+    }
+
+    // Runs depth-first iterations with a growing f-bound. Only the current
+    // path is kept in memory, and it doubles as the cycle check.
+    fn iterate<O: Observer<S>>(
+        &self,
+        find_all: bool,
+        observer: &mut O,
+    ) -> (Vec<Solution<S>>, SearchStats) {
+        let mut progress = Progress {
+            stats: SearchStats::default(),
+            results: Vec::new(),
+            find_all,
+            observer,
+        };
+        let mut threshold = self.heuristic.estimate(&self.init_state);
+        let mut path = vec![self.init_state.clone()];
+
+        loop {
+            match self.probe(&mut path, 0f64, threshold, &mut progress) {
+                Probe::Exceeded(next_threshold) if progress.results.is_empty() => {
+                    threshold = next_threshold
+                }
+                _ => return (progress.results, progress.stats),
+            }
+        }
+    }
+
+    fn probe<O: Observer<S>>(
+        &self,
+        path: &mut Vec<S>,
+        cost: f64,
+        threshold: f64,
+        progress: &mut Progress<S, O>,
+    ) -> Probe {
+        let state = path.last().unwrap().clone();
+        let estimate = cost + self.heuristic.estimate(&state);
+        if estimate > threshold {
+            return Probe::Exceeded(estimate);
+        }
+
+        if state == self.complete_state {
+            progress.results.push(Solution {
+                path: path.clone(),
+                cost,
+            });
+            return if progress.find_all {
+                Probe::Exhausted
+            } else {
+                Probe::Found
+            };
+        }
+
+        progress.stats.expanded += 1;
+        progress.stats.max_depth = progress.stats.max_depth.max(path.len());
+        // The path is both the frontier and everything held to check for
+        // cycles, so its longest length stands in for both peaks.
+        progress.stats.peak_frontier = progress.stats.peak_frontier.max(path.len());
+        progress.stats.peak_seen = progress.stats.peak_seen.max(path.len());
+        progress.observer.popped(&state, path.len() - 1);
+        let mut next_threshold = None;
+        for (next_state, step_cost) in state.next_weighted() {
+            progress.stats.generated += 1;
+            progress
+                .observer
+                .generated(&next_state, path.len(), cost + step_cost);
+            if path.contains(&next_state) {
+                progress.stats.duplicates += 1;
+                progress.observer.duplicate(&next_state);
+                continue;
+            }
+
+            path.push(next_state);
+            let probe = self.probe(path, cost + step_cost, threshold, progress);
+            if let Probe::Found = probe {
+                return probe;
+            }
+            path.pop();
+
+            if let Probe::Exceeded(estimate) = probe {
+                next_threshold = Some(match next_threshold {
+                    Some(current) if current < estimate => current,
+                    _ => estimate,
+                });
+            }
+        }
+
+        match next_threshold {
+            Some(estimate) => Probe::Exceeded(estimate),
+            None => Probe::Exhausted,
+        }
+    }
+}
+
+// IDA* keeps its own depth-first frontier, so the `StateBox` parameter is
+// accepted for compatibility with `Solver` but otherwise unused.
+impl<S: State, Hr: Heuristic<S>> Solver<S> for IDAStar<S, Hr> {
+    type Supplier = ();
+
+    fn search_observed<SB: StateBox<S>, O: Observer<S>>(self, observer: &mut O) -> SearchReport<S>
+    where
+        (): Supply<SB::Heuristic>,
+    {
+        let started = Instant::now();
+        let (mut results, stats) = self.iterate(false, observer);
+        let outcome = match results.pop() {
+            Some(solution) => SearchOutcome::Found(solution),
+            None => SearchOutcome::Exhausted,
+        };
+        SearchReport::new(outcome, stats, started, observer)
+    }
+
+    fn find_all_observed<SB: StateBox<S>, O: Observer<S>>(self, observer: &mut O) -> Vec<Vec<S>>
+    where
+        (): Supply<SB::Heuristic>,
+    {
+        let started = Instant::now();
+        let (results, mut stats) = self.iterate(true, observer);
+        results
+            .iter()
+            .for_each(|solution| observer.goal_found(solution));
+        stats.conclude(stats.max_depth, started);
+        observer.finished(&stats);
+        results.into_iter().map(|solution| solution.path).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    pub use super::*;
+    use crate::{
+        boxes::BFSBox,
+        distances::Manhattan,
+        testing::{board, is_valid_path},
+        tileboard::TileBoard,
+    };
+
+    #[test]
+    fn finds_valid_tileboard_solutions_alright() {
+        let init: TileBoard<3, 3> = board(20);
+        let solver = IDAStar::<_, Manhattan>::new(init.clone(), TileBoard::default());
+        let (path, _) = Solver::<_>::find_one_with_checks::<BFSBox<_>>(solver).unwrap();
+
+        assert_eq!(path.first(), Some(&init));
+        assert_eq!(path.last(), Some(&TileBoard::default()));
+        assert!(is_valid_path(&path));
+    }
+
+    #[test]
+    fn finds_all_solutions_within_the_same_bound_alright() {
+        let init: TileBoard<3, 2> = board(12);
+        let solver = IDAStar::<_, Manhattan>::new(init, TileBoard::default());
+        let results = Solver::<_>::find_all::<BFSBox<_>>(solver);
+
+        assert!(!results.is_empty());
+        for path in results {
+            assert_eq!(path.last(), Some(&TileBoard::default()));
+            assert!(is_valid_path(&path));
+        }
+    }
+
+    #[derive(Default)]
+    struct Finished(Option<SearchStats>);
+
+    impl<S: State> Observer<S> for Finished {
+        fn finished(&mut self, stats: &SearchStats) {
+            self.0 = Some(stats.clone());
+        }
+    }
+
+    #[test]
+    fn reports_its_stats_alright() {
+        let init: TileBoard<3, 2> = board(12);
+        let solver = IDAStar::<_, Manhattan>::new(init, TileBoard::default());
+        let mut finished = Finished::default();
+        let results = Solver::<_>::find_all_observed::<BFSBox<_>, _>(solver, &mut finished);
+        let stats = finished.0.unwrap();
+
+        assert!(!results.is_empty());
+        assert!(stats.branching_factor > 0f64);
+        assert_eq!(stats.peak_frontier, stats.max_depth);
+        assert_eq!(stats.peak_seen, stats.max_depth);
+    }
+}
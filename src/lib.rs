@@ -1,13 +1,25 @@
 use std::fmt::Debug;
 
+pub mod anytime;
+pub mod bidirectional;
 pub mod boxes;
 pub mod distances;
+pub mod goals;
+pub mod hda;
+pub mod heuristics;
+pub mod idastar;
 pub mod machine;
+pub mod pdb;
+pub mod search;
 pub mod sharedlist;
+pub mod solutions;
 pub mod tileboard;
 pub mod traits;
 pub mod hanoi;
 
+#[cfg(test)]
+mod testing;
+
 #[derive(PartialEq, Eq, Clone, Copy)]
 pub struct Coord {
     column: usize,
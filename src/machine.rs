@@ -1,78 +1,401 @@
-use std::collections::HashSet;
+use std::{
+    collections::{HashMap, HashSet},
+    time::Instant,
+};
 
-use crate::traits::{Solver, State, StateBox};
+use crate::{
+    bidirectional::Bidirectional,
+    boxes::UniformCostBox,
+    goals::Exact,
+    search::{SearchConfig, SearchReport},
+    solutions::Solutions,
+    traits::{Given, Goal, Observer, Solver, State, StateBox, Supply},
+};
 
 #[derive(Clone)]
-pub struct Machine<S: State> {
+pub struct Machine<S: State, G: Goal<S> = Exact<S>, Su = ()> {
     init_state: S,
-    complete_state: S,
+    goal: G,
+    config: SearchConfig,
+    supplier: Su,
 }
 
 impl<S: State> Machine<S> {
     pub fn new(init_state: S, complete_state: S) -> Self {
+        Machine::with_goal(init_state, Exact(complete_state))
+    }
+
+    pub fn bidirectional(self) -> Bidirectional<S> {
+        Bidirectional::new(self.init_state, self.goal.0).with_config(self.config)
+    }
+}
+
+impl<S: State, G: Goal<S>> Machine<S, G> {
+    pub fn with_goal(init_state: S, goal: G) -> Self {
         Machine {
             init_state,
-            complete_state,
+            goal,
+            config: SearchConfig::default(),
+            supplier: (),
         }
     }
 }
 
-impl<S: State> Solver<S> for Machine<S> {
-    fn find_one_with_checks<SB: StateBox<S>>(self) -> Option<(Vec<S>, usize)> {
-        if self.init_state == self.complete_state {
-            return Some((vec![self.init_state], 0));
+impl<S: State, G: Goal<S>, Su> Machine<S, G, Su> {
+    pub fn with_config(mut self, config: SearchConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    // Hands every box this machine searches with a clone of `heuristic`
+    // instead of letting it build its own.
+    pub fn with_heuristic<Hr>(self, heuristic: Hr) -> Machine<S, G, Given<Hr>> {
+        Machine {
+            init_state: self.init_state,
+            goal: self.goal,
+            config: self.config,
+            supplier: Given(heuristic),
         }
+    }
 
-        let mut checks = 0;
-        let mut seen = HashSet::new();
-        let mut unprocessed_states = SB::init(self.init_state);
-        while let Some((state, history)) = unprocessed_states.pop() {
-            checks += 1;
-            let next_states = state.next();
-            for next_state in next_states {
-                let new_history = history.push(state.clone());
-                if next_state == self.complete_state {
-                    let complete_history = new_history.push(next_state);
-                    return Some((complete_history.into(), checks));
-                }
-                if !seen.contains(&next_state) {
-                    seen.insert(next_state.clone());
-                    unprocessed_states.insert(next_state, new_history);
+    pub fn solutions<SB: StateBox<S>>(self) -> Solutions<S, G, SB>
+    where
+        Su: Supply<SB::Heuristic>,
+    {
+        self.solutions_observed(())
+    }
+
+    pub fn solutions_observed<SB: StateBox<S>, O: Observer<S>>(
+        self,
+        observer: O,
+    ) -> Solutions<S, G, SB, O>
+    where
+        Su: Supply<SB::Heuristic>,
+    {
+        let heuristic = self.supplier.supply();
+        Solutions::new(self.init_state, self.goal, self.config, heuristic, observer)
+    }
+
+    // Pops states cheapest first, keeping every parent that reaches a state at
+    // its cheapest cost, then unwinds those parent lists from each goal that
+    // comes out at the cost of the first. Stops early, with whatever it has
+    // found, on cancellation or when a limit in the config is hit.
+    pub fn find_all_shortest(self) -> Vec<Vec<S>> {
+        let started = Instant::now();
+        let mut best = HashMap::new();
+        best.insert(self.init_state.clone(), 0f64);
+        let mut parents = HashMap::new();
+        parents.insert(self.init_state.clone(), Vec::new());
+        // Equal-cost parents are only taken from states expanded before the
+        // child, so that zero-cost cycles cannot make the parent lists loop.
+        let mut expanded = HashSet::new();
+        let mut queue = UniformCostBox::<S>::init(self.init_state, ());
+        let mut goals = Vec::new();
+        let mut goal_cost = None;
+
+        while let Some((state, _, cost)) = queue.pop() {
+            if goal_cost.is_some_and(|goal_cost| cost > goal_cost) {
+                break;
+            }
+            if expanded.contains(&state) {
+                continue;
+            }
+            if self.goal.is_complete(&state) {
+                goal_cost = Some(cost);
+                goals.push(state);
+                continue;
+            }
+            if self.config.cancelled()
+                || self
+                    .config
+                    .exceeded(expanded.len(), queue.len(), started)
+                    .is_some()
+            {
+                break;
+            }
+
+            expanded.insert(state.clone());
+            for (next_state, step_cost) in state.next_weighted() {
+                let new_cost = cost + step_cost;
+                match best.get(&next_state) {
+                    Some(&cheapest) if cheapest < new_cost => {}
+                    Some(&cheapest) if cheapest == new_cost => {
+                        if !expanded.contains(&next_state) {
+                            parents.get_mut(&next_state).unwrap().push(state.clone());
+                        }
+                    }
+                    _ => {
+                        best.insert(next_state.clone(), new_cost);
+                        parents.insert(next_state.clone(), vec![state.clone()]);
+                        queue.insert(next_state, Default::default(), new_cost);
+                    }
                 }
             }
         }
 
-        None
+        unwind(&parents, goals)
     }
 
-    fn find_one<SB: StateBox<S>>(self) -> Option<Vec<S>> {
-        Solver::<S>::find_one_with_checks::<SB>(self).map(|(res, _)| res)
+    // Every path that reaches a goal within `max_moves` without visiting a
+    // state twice. Paths end at the first goal they reach. There can be
+    // exponentially many, so this stops early, with whatever it has found, on
+    // cancellation or when a limit in the config is hit.
+    pub fn find_all_simple(self, max_moves: usize) -> Vec<Vec<S>> {
+        let mut results = Vec::new();
+        let mut on_path = HashSet::new();
+        on_path.insert(self.init_state.clone());
+        let mut path = vec![self.init_state.clone()];
+        let mut walk = Walk {
+            started: Instant::now(),
+            expanded: 0,
+            max_moves,
+        };
+        self.extend_simple(&mut path, &mut on_path, &mut walk, &mut results);
+        results
     }
 
-    fn find_all<SB: StateBox<S>>(self) -> Vec<Vec<S>> {
-        let mut results = Vec::new();
+    // Returns false once the walk has to stop.
+    fn extend_simple(
+        &self,
+        path: &mut Vec<S>,
+        on_path: &mut HashSet<S>,
+        walk: &mut Walk,
+        results: &mut Vec<Vec<S>>,
+    ) -> bool {
+        let state = path.last().unwrap().clone();
+        if self.goal.is_complete(&state) {
+            results.push(path.clone());
+            return true;
+        }
+        if path.len() > walk.max_moves {
+            return true;
+        }
+        if self.config.cancelled()
+            || self
+                .config
+                .exceeded(walk.expanded, path.len(), walk.started)
+                .is_some()
+        {
+            return false;
+        }
+
+        walk.expanded += 1;
+        for next_state in state.next() {
+            if on_path.contains(&next_state) {
+                continue;
+            }
+            on_path.insert(next_state.clone());
+            path.push(next_state);
+            let going = self.extend_simple(path, on_path, walk, results);
+            on_path.remove(&path.pop().unwrap());
+            if !going {
+                return false;
+            }
+        }
+        true
+    }
+}
 
-        if self.init_state == self.complete_state {
-            results.push(vec![self.init_state.clone()]);
+// How far `find_all_simple` has got, for checking it against the config.
+struct Walk {
+    started: Instant,
+    expanded: usize,
+    max_moves: usize,
+}
+
+fn unwind<S: State>(parents: &HashMap<S, Vec<S>>, goals: Vec<S>) -> Vec<Vec<S>> {
+    let mut results = Vec::new();
+    let mut partial_paths: Vec<Vec<S>> = goals.into_iter().map(|goal| vec![goal]).collect();
+    while let Some(mut path) = partial_paths.pop() {
+        let state_parents = &parents[path.last().unwrap()];
+        if state_parents.is_empty() {
+            path.reverse();
+            results.push(path);
+            continue;
+        }
+        for parent in state_parents {
+            let mut longer_path = path.clone();
+            longer_path.push(parent.clone());
+            partial_paths.push(longer_path);
         }
+    }
+    results
+}
 
-        let mut seen = HashSet::new();
-        let mut unprocessed_states = SB::init(self.init_state);
-        while let Some((state, history)) = unprocessed_states.pop() {
-            let next_states = state.next();
-            for next_state in next_states {
-                let new_history = history.push(state.clone());
-                if next_state == self.complete_state {
-                    let complete_history = new_history.push(next_state.clone());
-                    results.push(complete_history.into());
-                }
-                if !seen.contains(&next_state) {
-                    seen.insert(next_state.clone());
-                    unprocessed_states.insert(next_state, new_history);
-                }
+impl<S: State, G: Goal<S>, Su> Solver<S> for Machine<S, G, Su> {
+    type Supplier = Su;
+
+    fn search_observed<SB: StateBox<S>, O: Observer<S>>(self, observer: &mut O) -> SearchReport<S>
+    where
+        Su: Supply<SB::Heuristic>,
+    {
+        self.solutions_observed::<SB, _>(observer).into_report()
+    }
+
+    // Shares one `seen` set across every branch, so each state is reached by a
+    // single path. Use `find_all_shortest` or `find_all_simple` when every
+    // route to the goal matters.
+    fn find_all_observed<SB: StateBox<S>, O: Observer<S>>(self, observer: &mut O) -> Vec<Vec<S>>
+    where
+        Su: Supply<SB::Heuristic>,
+    {
+        self.solutions_observed::<SB, _>(observer)
+            .map(|solution| solution.path)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    pub use super::*;
+    use crate::{
+        boxes::AStarBox, distances::Manhattan, search::Cancellation, traits::Heuristic, Coord,
+    };
+
+    // 0 -> 1 -> 3 and 0 -> 2 -> 3 are both shortest, 0 -> 4 -> 5 -> 3 is not.
+    #[derive(Clone, PartialEq, Eq, Hash, Debug)]
+    struct Diamond(usize);
+
+    impl State for Diamond {
+        type Point = Coord;
+
+        fn next(&self) -> Vec<Self> {
+            match self.0 {
+                0 => vec![Diamond(1), Diamond(2), Diamond(4)],
+                1 | 2 | 5 => vec![Diamond(3)],
+                4 => vec![Diamond(5)],
+                _ => vec![Diamond(0)],
             }
         }
 
-        results
+        fn differences(&self) -> Vec<(Self::Point, Self::Point)> {
+            Vec::new()
+        }
+    }
+
+    fn sorted(mut paths: Vec<Vec<Diamond>>) -> Vec<Vec<usize>> {
+        paths.sort_by_key(|path| path.iter().map(|d| d.0).collect::<Vec<_>>());
+        paths
+            .into_iter()
+            .map(|path| path.into_iter().map(|d| d.0).collect())
+            .collect()
+    }
+
+    #[test]
+    fn finds_every_shortest_path_alright() {
+        let machine = Machine::new(Diamond(0), Diamond(3));
+        assert_eq!(
+            sorted(machine.find_all_shortest()),
+            vec![vec![0, 1, 3], vec![0, 2, 3]]
+        );
+    }
+
+    #[test]
+    fn finds_every_simple_path_alright() {
+        let machine = Machine::new(Diamond(0), Diamond(3));
+        assert_eq!(
+            sorted(machine.clone().find_all_simple(2)),
+            vec![vec![0, 1, 3], vec![0, 2, 3]]
+        );
+        assert_eq!(
+            sorted(machine.find_all_simple(3)),
+            vec![vec![0, 1, 3], vec![0, 2, 3], vec![0, 4, 5, 3]]
+        );
+    }
+
+    // The heuristic is admissible but overestimates at A, so B is first
+    // expanded along the dearer direct edge. Only re-opening B, and so G, once
+    // the route through A turns up finds the 5-cost path S -> A -> B -> G.
+    #[derive(Clone, PartialEq, Eq, Hash, Debug)]
+    enum Detour {
+        S,
+        A,
+        B,
+        G,
+    }
+
+    impl State for Detour {
+        type Point = Coord;
+
+        fn next(&self) -> Vec<Self> {
+            self.next_weighted().into_iter().map(|(d, _)| d).collect()
+        }
+
+        fn differences(&self) -> Vec<(Self::Point, Self::Point)> {
+            let estimate = if *self == Detour::A { 4 } else { 0 };
+            vec![(Coord::new(estimate, 0), Coord::new(0, 0))]
+        }
+
+        fn next_weighted(&self) -> Vec<(Self, f64)> {
+            match self {
+                Detour::S => vec![(Detour::A, 1f64), (Detour::B, 3f64)],
+                Detour::A => vec![(Detour::B, 1f64)],
+                Detour::B => vec![(Detour::G, 3f64)],
+                Detour::G => vec![],
+            }
+        }
+    }
+
+    #[test]
+    fn finds_every_cheapest_path_alright() {
+        let machine = Machine::new(Detour::S, Detour::G);
+        assert_eq!(
+            machine.find_all_shortest(),
+            vec![vec![Detour::S, Detour::A, Detour::B, Detour::G]]
+        );
+    }
+
+    #[test]
+    fn stops_walking_simple_paths_alright() {
+        let config = SearchConfig {
+            max_expansions: Some(2),
+            ..SearchConfig::default()
+        };
+        let machine = Machine::new(Diamond(0), Diamond(3)).with_config(config);
+        assert_eq!(sorted(machine.find_all_simple(3)), vec![vec![0, 1, 3]]);
+
+        let cancellation = Cancellation::new();
+        cancellation.cancel();
+        let config = SearchConfig {
+            cancellation: Some(cancellation),
+            ..SearchConfig::default()
+        };
+        let machine = Machine::new(Diamond(0), Diamond(3)).with_config(config);
+        assert!(machine.clone().find_all_simple(3).is_empty());
+        assert!(machine.find_all_shortest().is_empty());
+    }
+
+    #[test]
+    fn reopens_cheaper_paths_alright() {
+        let machine = Machine::new(Detour::S, Detour::G);
+        let report = Solver::<_>::search::<AStarBox<_, Manhattan>>(machine);
+        assert_eq!(report.stats.reopened, 2);
+
+        let (solution, _) = report.solution().unwrap();
+        assert_eq!(
+            solution.path,
+            vec![Detour::S, Detour::A, Detour::B, Detour::G]
+        );
+        assert_eq!(solution.cost, 5f64);
+    }
+    // Manhattan shrunk by a factor only known at runtime, so there is no
+    // default for a box to build.
+    #[derive(Clone)]
+    struct Shrunk(f64);
+
+    impl<S: State<Point = Coord>> Heuristic<S> for Shrunk {
+        fn estimate(&self, state: &S) -> f64 {
+            self.0 * Manhattan.estimate(state)
+        }
+    }
+
+    #[test]
+    fn hands_boxes_a_heuristic_alright() {
+        let machine = Machine::new(Detour::S, Detour::G).with_heuristic(Shrunk(0.25));
+        let report = Solver::<_>::search::<AStarBox<_, _>>(machine);
+        // A now comes out before B, so B is only re-queued while still waiting
+        // and G is only ever reached along the cheaper path.
+        assert_eq!(report.stats.reopened, 1);
+        assert_eq!(report.solution().unwrap().0.cost, 5f64);
     }
 }
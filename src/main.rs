@@ -1,6 +1,6 @@
 use std::fmt::Debug;
 
-use statemachine::{boxes::BFSBox, hanoi::Hanoi, machine::Machine, traits::{Solver, State, StateBox}};
+use statemachine::{boxes::BFSBox, hanoi::Hanoi, machine::Machine, search::SearchOutcome, traits::{Solver, State, StateBox, Supply}};
 
 fn main() {
     let state: Hanoi<12> = Hanoi::new();
@@ -12,16 +12,25 @@ fn main() {
     run_and_report::<_, BFSBox<_>, _>(machine);
 }
 
-fn run_and_report<S: State + Debug, SB: StateBox<S>, SO: Solver<S>>(solver: SO) {
-    let solution = Solver::<_>::find_one_with_checks::<SB>(solver);
-    if let Some((history, turns)) = solution {
-        println!(
-            "{}: took {} turns to find a solution of length {}",
+fn run_and_report<S: State + Debug, SB: StateBox<S>, SO: Solver<S>>(solver: SO)
+where
+    SO::Supplier: Supply<SB::Heuristic>,
+{
+    let report = Solver::<_>::search::<SB>(solver);
+    match report.outcome {
+        SearchOutcome::Found(solution) => println!(
+            "{}: took {} turns to find a solution of length {} and cost {}",
             std::any::type_name::<SB>(),
-            turns,
-            history.len()
-        );
-    } else {
-        println!("no solution found 😥");
+            report.stats.expanded,
+            solution.path.len(),
+            solution.cost
+        ),
+        SearchOutcome::Exhausted => println!("no solution found 😥"),
+        SearchOutcome::LimitReached(limit) => println!(
+            "gave up after {} turns, the {:?} limit was hit",
+            report.stats.expanded, limit
+        ),
+        SearchOutcome::Cancelled => println!("cancelled after {} turns", report.stats.expanded),
     }
+    println!("{}", report.stats);
 }
@@ -0,0 +1,511 @@
+use std::{
+    collections::VecDeque,
+    convert::TryFrom,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+    sync::Arc,
+};
+
+use arrayvec::ArrayVec;
+
+use crate::{
+    tileboard::TileBoard,
+    traits::{Heuristic, Pattern, State},
+};
+
+// The most tiles a group of the default disjoint patterns holds.
+pub const DEFAULT_GROUP_SIZE: usize = 5;
+
+// Table entry for abstract states the walk from the goal never reached.
+const UNREACHED: u8 = u8::MAX;
+
+const DATABASE_MAGIC: &[u8; 4] = b"PDB1";
+const DISJOINT_MAGIC: &[u8; 4] = b"DPD1";
+
+// Exact distances to the goal in an abstraction of the state space. Every
+// abstract move stands for at least one real one, so the distance of the
+// pattern a state maps onto never overestimates the state's own.
+#[derive(Clone)]
+pub struct PatternDatabase<S, P> {
+    abstraction: fn(&S) -> P,
+    distances: Arc<[u8]>,
+}
+
+// Pattern databases over disjoint groups of tiles that only count moves of
+// their own tiles, so their estimates can be added up. The blank is left out
+// of each table, which keeps the fewest moves over wherever it could be.
+#[derive(Clone)]
+pub struct DisjointPatterns<const C: usize, const R: usize> {
+    groups: Arc<Vec<TileGroup>>,
+}
+
+struct TileGroup {
+    tiles: Vec<usize>,
+    distances: Vec<u8>,
+}
+
+impl<S: State, P: Pattern> PatternDatabase<S, P> {
+    // Walks the abstract space breadth first out from the goal's pattern. The
+    // walk follows `next`, so it only finds distances to the goal when every
+    // abstract move can be undone, as sliding a tile can.
+    pub fn build(goal: &S, abstraction: fn(&S) -> P) -> Self {
+        let goal = abstraction(goal);
+        let mut distances = Vec::new();
+        record(&mut distances, goal.rank(), 0);
+
+        let mut queue = VecDeque::from([goal]);
+        while let Some(pattern) = queue.pop_front() {
+            let distance = distances[pattern.rank()]
+                .saturating_add(1)
+                .min(UNREACHED - 1);
+            for next in pattern.next() {
+                let rank = next.rank();
+                if distances.get(rank).is_none_or(|&known| known == UNREACHED) {
+                    record(&mut distances, rank, distance);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        PatternDatabase {
+            abstraction,
+            distances: distances.into(),
+        }
+    }
+
+    pub fn save<Pa: AsRef<Path>>(&self, path: Pa) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(DATABASE_MAGIC)?;
+        write_table(&mut writer, &self.distances)?;
+        writer.flush()
+    }
+
+    // The abstraction isn't saved along with the table, so it has to be the
+    // one the database was built with.
+    pub fn load<Pa: AsRef<Path>>(path: Pa, abstraction: fn(&S) -> P) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        expect_magic(&mut reader, DATABASE_MAGIC)?;
+        Ok(PatternDatabase {
+            abstraction,
+            distances: read_table(&mut reader)?.into(),
+        })
+    }
+}
+
+impl<S: State, P: Pattern> Heuristic<S> for PatternDatabase<S, P> {
+    fn estimate(&self, state: &S) -> f64 {
+        lookup(&self.distances, (self.abstraction)(state).rank())
+    }
+}
+
+impl<const C: usize, const R: usize> DisjointPatterns<C, R> {
+    pub fn new(groups: &[&[usize]]) -> Self {
+        let mut used = vec![false; C * R];
+        for &tile in groups.iter().flat_map(|tiles| tiles.iter()) {
+            assert!(
+                tile > 0 && tile < C * R && !used[tile],
+                "tile {} isn't a numbered tile in exactly one group",
+                tile
+            );
+            used[tile] = true;
+        }
+
+        let groups = groups
+            .iter()
+            .map(|tiles| TileGroup::build::<C, R>(tiles))
+            .collect();
+        DisjointPatterns {
+            groups: Arc::new(groups),
+        }
+    }
+
+    pub fn save<Pa: AsRef<Path>>(&self, path: Pa) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(DISJOINT_MAGIC)?;
+        write_number(&mut writer, C)?;
+        write_number(&mut writer, R)?;
+        write_number(&mut writer, self.groups.len())?;
+        for group in self.groups.iter() {
+            write_number(&mut writer, group.tiles.len())?;
+            for &tile in &group.tiles {
+                write_number(&mut writer, tile)?;
+            }
+            write_table(&mut writer, &group.distances)?;
+        }
+        writer.flush()
+    }
+
+    pub fn load<Pa: AsRef<Path>>(path: Pa) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        expect_magic(&mut reader, DISJOINT_MAGIC)?;
+        if read_number(&mut reader)? != C || read_number(&mut reader)? != R {
+            return Err(invalid("patterns are for another board size"));
+        }
+
+        let mut used = vec![false; C * R];
+        let mut groups = Vec::new();
+        for _ in 0..read_number(&mut reader)? {
+            let mut tiles = Vec::new();
+            for _ in 0..read_number(&mut reader)? {
+                let tile = read_number(&mut reader)?;
+                if tile == 0 || tile >= C * R || used[tile] {
+                    return Err(invalid("groups overlap or hold a tile the board lacks"));
+                }
+                used[tile] = true;
+                tiles.push(tile);
+            }
+
+            let distances = read_table(&mut reader)?;
+            if distances.len() != arrangements(tiles.len(), C * R) {
+                return Err(invalid("table doesn't fit its group"));
+            }
+            groups.push(TileGroup { tiles, distances });
+        }
+
+        Ok(DisjointPatterns {
+            groups: Arc::new(groups),
+        })
+    }
+}
+
+// Splits the numbered tiles in order into the fewest even groups that hold
+// no more than `DEFAULT_GROUP_SIZE` each. Building them takes a while on
+// larger boards, so build them once and hand clones to each search.
+impl<const C: usize, const R: usize> Default for DisjointPatterns<C, R> {
+    fn default() -> Self {
+        let tiles = C * R - 1;
+        let count = tiles.div_ceil(DEFAULT_GROUP_SIZE);
+        let groups: Vec<Vec<usize>> = (0..count)
+            .map(|i| (i * tiles / count + 1..(i + 1) * tiles / count + 1).collect())
+            .collect();
+        let groups: Vec<&[usize]> = groups.iter().map(Vec::as_slice).collect();
+        DisjointPatterns::new(&groups)
+    }
+}
+
+impl<const C: usize, const R: usize> Heuristic<TileBoard<C, R>> for DisjointPatterns<C, R> {
+    fn estimate(&self, board: &TileBoard<C, R>) -> f64 {
+        let mut places = vec![0; C * R];
+        for cell in 0..C * R {
+            places[board.tile(cell % C, cell / C)] = cell;
+        }
+
+        let mut positions = Vec::new();
+        self.groups
+            .iter()
+            .map(|group| {
+                positions.clear();
+                positions.extend(group.tiles.iter().map(|&tile| places[tile]));
+                lookup(&group.distances, rank(&positions, C * R))
+            })
+            .sum()
+    }
+}
+
+impl TileGroup {
+    // A 0-1 breadth-first walk out from the goal over where the group's tiles
+    // and the blank can be. Moving the blank onto one of the group's tiles
+    // moves that tile and costs one, while moving it anywhere else is free.
+    fn build<const C: usize, const R: usize>(tiles: &[usize]) -> Self {
+        let cells = C * R;
+        let count = tiles.len() + 1;
+        let mut walked = vec![UNREACHED; arrangements(count, cells)];
+
+        let mut positions: Vec<usize> = tiles.iter().map(|&tile| tile - 1).collect();
+        positions.push(cells - 1);
+        let goal = rank(&positions, cells);
+        walked[goal] = 0;
+
+        let mut queue = VecDeque::from([goal]);
+        let mut next = Vec::with_capacity(count);
+        while let Some(current) = queue.pop_front() {
+            let distance = walked[current];
+            unrank(current, count, cells, &mut positions);
+            let blank = positions[count - 1];
+
+            for cell in neighbours::<C, R>(blank) {
+                next.clear();
+                next.extend_from_slice(&positions);
+                next[count - 1] = cell;
+                let moved = next[..count - 1].iter().position(|&tile| tile == cell);
+                if let Some(tile) = moved {
+                    next[tile] = blank;
+                }
+
+                let rank = rank(&next, cells);
+                let step = moved.is_some() as u8;
+                let distance = distance.saturating_add(step).min(UNREACHED - 1);
+                if distance < walked[rank] {
+                    walked[rank] = distance;
+                    if moved.is_some() {
+                        queue.push_back(rank);
+                    } else {
+                        queue.push_front(rank);
+                    }
+                }
+            }
+        }
+
+        // The blank's cell is the last and least significant part of a rank,
+        // so each run of that many entries shares the same tile positions.
+        let distances = walked
+            .chunks(cells - tiles.len())
+            .map(|blanks| *blanks.iter().min().unwrap())
+            .collect();
+        TileGroup {
+            tiles: tiles.to_vec(),
+            distances,
+        }
+    }
+}
+
+fn neighbours<const C: usize, const R: usize>(cell: usize) -> ArrayVec<usize, 4> {
+    let (column, row) = (cell % C, cell / C);
+    let mut res = ArrayVec::new();
+    if column > 0 {
+        res.push(cell - 1);
+    }
+    if column < C - 1 {
+        res.push(cell + 1);
+    }
+    if row > 0 {
+        res.push(cell - C);
+    }
+    if row < R - 1 {
+        res.push(cell + C);
+    }
+    res
+}
+
+// How many ways `count` distinct things can be placed among `cells` cells.
+fn arrangements(count: usize, cells: usize) -> usize {
+    (cells - count + 1..=cells).product()
+}
+
+// Numbers placements of distinct things densely from zero. Each position
+// becomes its index among the cells the earlier ones left free, read as a
+// digit whose base shrinks by one per position.
+fn rank(positions: &[usize], cells: usize) -> usize {
+    positions
+        .iter()
+        .enumerate()
+        .fold(0, |rank, (i, &position)| {
+            let taken = positions[..i].iter().filter(|&&p| p < position).count();
+            rank * (cells - i) + position - taken
+        })
+}
+
+fn unrank(mut rank: usize, count: usize, cells: usize, positions: &mut Vec<usize>) {
+    positions.clear();
+    positions.resize(count, 0);
+    for i in (0..count).rev() {
+        positions[i] = rank % (cells - i);
+        rank /= cells - i;
+    }
+
+    for i in 0..count {
+        let mut free = positions[i];
+        let mut cell = 0;
+        loop {
+            if !positions[..i].contains(&cell) {
+                if free == 0 {
+                    break;
+                }
+                free -= 1;
+            }
+            cell += 1;
+        }
+        positions[i] = cell;
+    }
+}
+
+fn record(distances: &mut Vec<u8>, rank: usize, distance: u8) {
+    if distances.len() <= rank {
+        distances.resize(rank + 1, UNREACHED);
+    }
+    distances[rank] = distance;
+}
+
+// Patterns the walk never reached can't lead to the goal at all, so any
+// estimate for them is safe and zero is the cheapest to reason about.
+fn lookup(distances: &[u8], rank: usize) -> f64 {
+    match distances.get(rank) {
+        Some(&distance) if distance != UNREACHED => distance as f64,
+        _ => 0f64,
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn expect_magic<Rd: Read>(reader: &mut Rd, magic: &[u8; 4]) -> io::Result<()> {
+    let mut found = [0; 4];
+    reader.read_exact(&mut found)?;
+    if &found == magic {
+        Ok(())
+    } else {
+        Err(invalid("not a pattern database of this kind"))
+    }
+}
+
+fn write_number<W: Write>(writer: &mut W, number: usize) -> io::Result<()> {
+    writer.write_all(&(number as u64).to_le_bytes())
+}
+
+fn read_number<Rd: Read>(reader: &mut Rd) -> io::Result<usize> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    usize::try_from(u64::from_le_bytes(bytes)).map_err(|_| invalid("number out of range"))
+}
+
+fn write_table<W: Write>(writer: &mut W, table: &[u8]) -> io::Result<()> {
+    write_number(writer, table.len())?;
+    writer.write_all(table)
+}
+
+fn read_table<Rd: Read>(reader: &mut Rd) -> io::Result<Vec<u8>> {
+    let len = read_number(reader)?;
+    let mut table = Vec::new();
+    reader.take(len as u64).read_to_end(&mut table)?;
+    if table.len() == len {
+        Ok(table)
+    } else {
+        Err(invalid("table cut short"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    pub use super::*;
+    use crate::{
+        boxes::{AStarBox, UniformCostBox},
+        machine::Machine,
+        testing::boards,
+        traits::Solver,
+        Coord,
+    };
+
+    // Where the blank and tiles 1 and 2 are on a 3x2 board, with the other
+    // tiles all alike.
+    #[derive(Clone, PartialEq, Eq, Hash)]
+    struct Spots {
+        tiles: [usize; 2],
+        blank: usize,
+    }
+
+    impl State for Spots {
+        type Point = Coord;
+
+        fn next(&self) -> Vec<Self> {
+            neighbours::<3, 2>(self.blank)
+                .into_iter()
+                .map(|cell| {
+                    let mut next = self.clone();
+                    for tile in &mut next.tiles {
+                        if *tile == cell {
+                            *tile = self.blank;
+                        }
+                    }
+                    next.blank = cell;
+                    next
+                })
+                .collect()
+        }
+
+        fn differences(&self) -> Vec<(Coord, Coord)> {
+            Vec::new()
+        }
+    }
+
+    impl Pattern for Spots {
+        fn rank(&self) -> usize {
+            rank(&[self.tiles[0], self.tiles[1], self.blank], 6)
+        }
+    }
+
+    fn spots(board: &TileBoard<3, 2>) -> Spots {
+        let mut spots = Spots {
+            tiles: [0; 2],
+            blank: 0,
+        };
+        for cell in 0..6 {
+            match board.tile(cell % 3, cell / 3) {
+                0 => spots.blank = cell,
+                1 => spots.tiles[0] = cell,
+                2 => spots.tiles[1] = cell,
+                _ => {}
+            }
+        }
+        spots
+    }
+
+    fn cheapest<const C: usize, const R: usize>(init: &TileBoard<C, R>) -> f64 {
+        let machine = Machine::new(init.clone(), TileBoard::default());
+        Solver::<_>::find_solution::<UniformCostBox<_>>(machine)
+            .unwrap()
+            .0
+            .cost
+    }
+
+    fn scratch_file(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("statemachine-{}-{}.pdb", name, std::process::id()))
+    }
+
+    #[test]
+    fn ranks_placements_densely_alright() {
+        let mut positions = Vec::new();
+        for r in 0..arrangements(3, 6) {
+            unrank(r, 3, 6, &mut positions);
+            assert!(positions.iter().all(|&p| p < 6));
+            assert_eq!(rank(&positions, 6), r);
+        }
+    }
+
+    #[test]
+    fn abstracts_any_state_alright() {
+        let database = PatternDatabase::build(&TileBoard::default(), spots);
+        let path = scratch_file("spots");
+        database.save(&path).unwrap();
+        let loaded = PatternDatabase::load(&path, spots).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(database.estimate(&TileBoard::default()), 0f64);
+        for init in boards::<3, 2>(10, 30) {
+            let cost = cheapest(&init);
+            assert!(database.estimate(&init) <= cost);
+            assert_eq!(loaded.estimate(&init), database.estimate(&init));
+
+            let machine = Machine::new(init, TileBoard::default()).with_heuristic(loaded.clone());
+            let (solution, _) = Solver::<_>::find_solution::<AStarBox<_, _>>(machine).unwrap();
+            assert_eq!(solution.cost, cost);
+        }
+    }
+
+    #[test]
+    fn adds_disjoint_patterns_alright() {
+        let patterns = DisjointPatterns::<3, 3>::default();
+        let path = scratch_file("disjoint");
+        patterns.save(&path).unwrap();
+        let loaded = DisjointPatterns::<3, 3>::load(&path).unwrap();
+        let other_size = DisjointPatterns::<4, 2>::load(&path).map(|_| ());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(other_size.unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let singles: Vec<Vec<usize>> = (1..9).map(|tile| vec![tile]).collect();
+        let singles: Vec<&[usize]> = singles.iter().map(|tiles| tiles.as_slice()).collect();
+        let manhattan = DisjointPatterns::<3, 3>::new(&singles);
+
+        for init in boards::<3, 3>(10, 40) {
+            let cost = cheapest(&init);
+            let estimate = patterns.estimate(&init);
+            assert!(manhattan.estimate(&init) <= estimate && estimate <= cost);
+            assert_eq!(loaded.estimate(&init), estimate);
+
+            let machine = Machine::new(init, TileBoard::default()).with_heuristic(loaded.clone());
+            let (solution, _) = Solver::<_>::find_solution::<AStarBox<_, _>>(machine).unwrap();
+            assert_eq!(solution.cost, cost);
+        }
+    }
+}
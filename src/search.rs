@@ -0,0 +1,358 @@
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    sync::{
+        atomic::{self, AtomicBool},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::traits::{Observer, State};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Solution<S> {
+    pub path: Vec<S>,
+    pub cost: f64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SearchConfig {
+    pub max_expansions: Option<usize>,
+    pub max_depth: Option<usize>,
+    pub max_frontier: Option<usize>,
+    pub beam_width: Option<usize>,
+    pub heuristic_weight: Option<f64>,
+    pub tie_break: Option<TieBreak>,
+    pub timeout: Option<Duration>,
+    pub cancellation: Option<Cancellation>,
+}
+
+// Which of several entries with the same score a priority box pops first.
+// Whatever the rule, entries it cannot separate pop in insertion order, so
+// runs with the same rule always expand the same states.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum TieBreak {
+    #[default]
+    HigherG,
+    LowerG,
+    LowerH,
+    Fifo,
+    Lifo,
+    // `Less` means the first entry pops first.
+    Custom(fn(&Tie, &Tie) -> Ordering),
+}
+
+// What a tie-break rule gets to see of an entry.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tie {
+    pub cost: f64,
+    pub heuristic: f64,
+    pub sequence: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Cancellation {
+    cancelled: Arc<AtomicBool>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    Expansions,
+    Depth,
+    Frontier,
+    Time,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SearchOutcome<S> {
+    Found(Solution<S>),
+    Exhausted,
+    LimitReached(Limit),
+    Cancelled,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchStats {
+    pub expanded: usize,
+    pub generated: usize,
+    pub duplicates: usize,
+    // States queued again because a cheaper path to them turned up.
+    pub reopened: usize,
+    pub peak_frontier: usize,
+    pub peak_seen: usize,
+    pub max_depth: usize,
+    pub branching_factor: f64,
+    pub suboptimality_bound: Option<f64>,
+    pub elapsed: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchReport<S> {
+    pub outcome: SearchOutcome<S>,
+    pub stats: SearchStats,
+}
+
+impl TieBreak {
+    pub fn compare(&self, a: &Tie, b: &Tie) -> Ordering {
+        match self {
+            TieBreak::HigherG => b.cost.total_cmp(&a.cost),
+            TieBreak::LowerG => a.cost.total_cmp(&b.cost),
+            TieBreak::LowerH => a.heuristic.total_cmp(&b.heuristic),
+            TieBreak::Fifo => a.sequence.cmp(&b.sequence),
+            TieBreak::Lifo => b.sequence.cmp(&a.sequence),
+            TieBreak::Custom(compare) => compare(a, b),
+        }
+        .then_with(|| a.sequence.cmp(&b.sequence))
+    }
+}
+
+impl Cancellation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, atomic::Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(atomic::Ordering::Relaxed)
+    }
+}
+
+impl SearchConfig {
+    pub(crate) fn exceeded(
+        &self,
+        expanded: usize,
+        frontier: usize,
+        started: Instant,
+    ) -> Option<Limit> {
+        if self.max_expansions.is_some_and(|max| expanded >= max) {
+            return Some(Limit::Expansions);
+        }
+        if self.max_frontier.is_some_and(|max| frontier > max) {
+            return Some(Limit::Frontier);
+        }
+        if self.timeout.is_some_and(|max| started.elapsed() >= max) {
+            return Some(Limit::Time);
+        }
+        None
+    }
+
+    pub(crate) fn cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(Cancellation::is_cancelled)
+    }
+
+    pub(crate) fn too_deep(&self, depth: usize) -> bool {
+        self.max_depth.is_some_and(|max| depth >= max)
+    }
+}
+
+impl SearchStats {
+    // Fills in what can only be worked out once the search has stopped, at
+    // the solution's depth or the deepest one reached.
+    pub(crate) fn conclude(&mut self, depth: usize, started: Instant) {
+        self.branching_factor = self.effective_branching_factor(depth);
+        self.elapsed = started.elapsed();
+    }
+
+    // Solves `generated = b + b^2 + ... + b^depth` for `b` by bisection, using
+    // the solution's depth when there is one and the deepest state otherwise.
+    fn effective_branching_factor(&self, depth: usize) -> f64 {
+        if depth == 0 || self.generated == 0 {
+            return 0f64;
+        }
+
+        let generated = self.generated as f64;
+        let total = |b: f64| (1..=depth).map(|d| b.powi(d as i32)).sum::<f64>();
+        let (mut low, mut high) = (0f64, generated.max(1f64));
+        for _ in 0..64 {
+            let mid = (low + high) / 2f64;
+            if total(mid) < generated {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        (low + high) / 2f64
+    }
+}
+
+impl Display for SearchStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "expanded:         {}", self.expanded)?;
+        writeln!(f, "generated:        {}", self.generated)?;
+        writeln!(f, "duplicates:       {}", self.duplicates)?;
+        writeln!(f, "reopened:         {}", self.reopened)?;
+        writeln!(f, "peak frontier:    {}", self.peak_frontier)?;
+        writeln!(f, "peak seen:        {}", self.peak_seen)?;
+        writeln!(f, "max depth:        {}", self.max_depth)?;
+        writeln!(f, "branching factor: {:.3}", self.branching_factor)?;
+        if let Some(bound) = self.suboptimality_bound {
+            writeln!(f, "suboptimality:    <= {:.3}", bound)?;
+        }
+        write!(f, "elapsed:          {:?}", self.elapsed)
+    }
+}
+
+impl<S: State> SearchReport<S> {
+    pub(crate) fn new<O: Observer<S>>(
+        outcome: SearchOutcome<S>,
+        mut stats: SearchStats,
+        started: Instant,
+        observer: &mut O,
+    ) -> Self {
+        let depth = match &outcome {
+            SearchOutcome::Found(solution) => {
+                observer.goal_found(solution);
+                solution.path.len().saturating_sub(1)
+            }
+            _ => stats.max_depth,
+        };
+        stats.conclude(depth, started);
+        observer.finished(&stats);
+        SearchReport { outcome, stats }
+    }
+}
+
+impl<S> SearchReport<S> {
+    pub fn solution(self) -> Option<(Solution<S>, usize)> {
+        match self.outcome {
+            SearchOutcome::Found(solution) => Some((solution, self.stats.expanded)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    pub use super::*;
+    use crate::{
+        anytime::ARAStar,
+        boxes::BFSBox,
+        distances::Euclidian,
+        goals::Predicate,
+        hanoi::Hanoi,
+        machine::Machine,
+        testing::board,
+        tileboard::TileBoard,
+        traits::{Observer, Solver},
+    };
+
+    #[derive(Default)]
+    struct Counter {
+        popped: usize,
+        generated: usize,
+        duplicates: usize,
+        goals: usize,
+        finished: Option<SearchStats>,
+    }
+
+    impl<S: State> Observer<S> for Counter {
+        fn popped(&mut self, _state: &S, _depth: usize) {
+            self.popped += 1;
+        }
+
+        fn generated(&mut self, _state: &S, _depth: usize, _cost: f64) {
+            self.generated += 1;
+        }
+
+        fn duplicate(&mut self, _state: &S) {
+            self.duplicates += 1;
+        }
+
+        fn goal_found(&mut self, _solution: &Solution<S>) {
+            self.goals += 1;
+        }
+
+        fn finished(&mut self, stats: &SearchStats) {
+            self.finished = Some(stats.clone());
+        }
+    }
+
+    #[test]
+    fn tells_limits_apart_from_exhaustion_alright() {
+        let config = SearchConfig {
+            max_expansions: Some(10),
+            ..SearchConfig::default()
+        };
+        let machine = Machine::new(Hanoi::<5>::new(), Hanoi::solved()).with_config(config);
+        let report = Solver::<_>::search::<BFSBox<_>>(machine);
+        assert_eq!(
+            report.outcome,
+            SearchOutcome::LimitReached(Limit::Expansions)
+        );
+        assert_eq!(report.stats.expanded, 10);
+
+        let config = SearchConfig {
+            max_depth: Some(3),
+            ..SearchConfig::default()
+        };
+        let machine = Machine::new(board::<3, 2>(40), TileBoard::default());
+        let report = Solver::<_>::search::<BFSBox<_>>(machine.with_config(config));
+        assert_eq!(report.outcome, SearchOutcome::LimitReached(Limit::Depth));
+
+        let machine = Machine::with_goal(Hanoi::<3>::new(), Predicate(|_: &Hanoi<3>| false));
+        let report = Solver::<_>::search::<BFSBox<_>>(machine);
+        assert_eq!(report.outcome, SearchOutcome::Exhausted);
+    }
+
+    #[test]
+    fn collects_stats_alright() {
+        let machine = Machine::new(Hanoi::<3>::new(), Hanoi::solved());
+        let report = Solver::<_>::search::<BFSBox<_>>(machine);
+        let stats = report.stats;
+
+        assert!(matches!(report.outcome, SearchOutcome::Found(_)));
+        assert_eq!(stats.max_depth, 7);
+        assert!(stats.peak_seen < 27);
+        assert!(stats.generated > stats.duplicates);
+        assert!(stats.generated >= stats.expanded);
+        assert!(stats.branching_factor > 1f64);
+    }
+
+    #[test]
+    fn notifies_observers_alright() {
+        let mut counter = Counter::default();
+        let machine = Machine::new(Hanoi::<4>::new(), Hanoi::solved());
+        let report = Solver::<_>::search_observed::<BFSBox<_>, _>(machine, &mut counter);
+
+        assert_eq!(counter.popped, report.stats.expanded);
+        assert_eq!(counter.generated, report.stats.generated);
+        assert_eq!(counter.duplicates, report.stats.duplicates);
+        assert_eq!(counter.goals, 1);
+        assert_eq!(counter.finished, Some(report.stats));
+
+        let mut counter = Counter::default();
+        let solver = ARAStar::<_, Euclidian>::new(board::<3, 3>(30), TileBoard::default());
+        let report = Solver::<_>::search_observed::<BFSBox<_>, _>(solver, &mut counter);
+
+        assert!(counter.popped > 0);
+        assert_eq!(counter.popped, report.stats.expanded);
+        assert_eq!(counter.generated, report.stats.generated);
+        assert_eq!(counter.duplicates, report.stats.duplicates);
+        assert_eq!(counter.goals, 1);
+        assert_eq!(counter.finished, Some(report.stats));
+    }
+
+    #[test]
+    fn stops_when_cancelled_alright() {
+        let cancellation = Cancellation::new();
+        let config = SearchConfig {
+            cancellation: Some(cancellation.clone()),
+            ..SearchConfig::default()
+        };
+        let machine = Machine::new(Hanoi::<14>::new(), Hanoi::solved()).with_config(config);
+        let worker = std::thread::spawn(move || Solver::<_>::search::<BFSBox<_>>(machine));
+
+        std::thread::sleep(Duration::from_millis(50));
+        cancellation.cancel();
+        let report = worker.join().unwrap();
+
+        assert_eq!(report.outcome, SearchOutcome::Cancelled);
+        assert!(report.stats.expanded > 0);
+    }
+}
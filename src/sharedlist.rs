@@ -1,31 +1,102 @@
-use std::rc::Rc;
+use std::{ops::Deref, rc::Rc, sync::Arc};
 
-#[derive(PartialEq, Eq, Clone)]
-pub enum SharedList<T> {
+use crate::traits::History;
+
+pub trait PointerKind {
+    type Pointer<V>: Deref<Target = V> + Clone;
+
+    fn new<V>(value: V) -> Self::Pointer<V>;
+    fn try_unwrap<V>(pointer: Self::Pointer<V>) -> Result<V, Self::Pointer<V>>;
+}
+
+pub struct RcKind;
+
+pub struct ArcKind;
+
+impl PointerKind for RcKind {
+    type Pointer<V> = Rc<V>;
+
+    fn new<V>(value: V) -> Self::Pointer<V> {
+        Rc::new(value)
+    }
+
+    fn try_unwrap<V>(pointer: Self::Pointer<V>) -> Result<V, Self::Pointer<V>> {
+        Rc::try_unwrap(pointer)
+    }
+}
+
+impl PointerKind for ArcKind {
+    type Pointer<V> = Arc<V>;
+
+    fn new<V>(value: V) -> Self::Pointer<V> {
+        Arc::new(value)
+    }
+
+    fn try_unwrap<V>(pointer: Self::Pointer<V>) -> Result<V, Self::Pointer<V>> {
+        Arc::try_unwrap(pointer)
+    }
+}
+
+pub type SyncSharedList<T> = SharedList<T, ArcKind>;
+
+pub enum SharedList<T, P: PointerKind = RcKind> {
     Empty,
-    Valued { length: usize, node: Rc<Node<T>> },
+    Valued {
+        length: usize,
+        node: P::Pointer<Node<T, P>>,
+    },
 }
 
-impl<T> Default for SharedList<T> {
+impl<T, P: PointerKind> Default for SharedList<T, P> {
     fn default() -> Self {
         SharedList::new()
     }
 }
 
-impl<T> SharedList<T> {
-    pub fn new() -> SharedList<T> {
+impl<T, P: PointerKind> Clone for SharedList<T, P> {
+    fn clone(&self) -> Self {
+        match self {
+            SharedList::Empty => SharedList::Empty,
+            SharedList::Valued { length, node } => SharedList::Valued {
+                length: *length,
+                node: node.clone(),
+            },
+        }
+    }
+}
+
+impl<T: PartialEq, P: PointerKind> PartialEq for SharedList<T, P> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (SharedList::Empty, SharedList::Empty) => true,
+            (
+                SharedList::Valued { length, node },
+                SharedList::Valued {
+                    length: other_length,
+                    node: other_node,
+                },
+            ) => length == other_length && **node == **other_node,
+            _ => false,
+        }
+    }
+}
+
+impl<T: Eq, P: PointerKind> Eq for SharedList<T, P> {}
+
+impl<T, P: PointerKind> SharedList<T, P> {
+    pub fn new() -> SharedList<T, P> {
         SharedList::Empty
     }
 
-    pub fn push(&self, value: T) -> SharedList<T> {
+    pub fn push(&self, value: T) -> SharedList<T, P> {
         match self {
             SharedList::Empty => SharedList::Valued {
                 length: 1,
-                node: Rc::new(Node::new(value, None)),
+                node: P::new(Node::new(value, None)),
             },
             SharedList::Valued { length, node } => SharedList::Valued {
                 length: length + 1,
-                node: Rc::new(Node::new(value, Some(Rc::clone(node)))),
+                node: P::new(Node::new(value, Some(node.clone()))),
             },
         }
     }
@@ -40,34 +111,153 @@ impl<T> SharedList<T> {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    // Walks from the most recently pushed value back to the first.
+    pub fn iter(&self) -> Iter<'_, T, P> {
+        Iter {
+            next: match self {
+                SharedList::Empty => None,
+                SharedList::Valued { node, .. } => Some(node),
+            },
+        }
+    }
+
+    // The most recently pushed value, which ends the path.
+    pub fn last(&self) -> Option<&T> {
+        self.iter().next()
+    }
+
+    // The first value pushed, which starts the path.
+    pub fn head(&self) -> Option<&T> {
+        self.iter().last()
+    }
 }
 
-impl<T: Clone> From<SharedList<T>> for Vec<T> {
-    fn from(s: SharedList<T>) -> Self {
-        let mut res = Vec::with_capacity(s.len());
+impl<'a, T, P: PointerKind> IntoIterator for &'a SharedList<T, P> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, P>;
 
-        if let SharedList::Valued { mut node, .. } = s {
-            res.push(node.value.clone());
-            while let Some(next_node) = &node.previous {
-                res.push(next_node.value.clone());
-                node = Rc::clone(next_node);
-            }
-        }
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
 
+impl<T: Clone, P: PointerKind> From<SharedList<T, P>> for Vec<T> {
+    fn from(s: SharedList<T, P>) -> Self {
+        let mut res = Vec::with_capacity(s.len());
+        res.extend(s.iter().cloned());
         res.reverse();
 
         res
     }
 }
 
-#[derive(PartialEq, Eq)]
-pub struct Node<T> {
+pub struct Iter<'a, T, P: PointerKind = RcKind> {
+    next: Option<&'a Node<T, P>>,
+}
+
+impl<'a, T, P: PointerKind> Iterator for Iter<'a, T, P> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.next?;
+        self.next = node.previous.as_deref();
+        Some(&node.value)
+    }
+}
+
+impl<T: Clone + PartialEq, P: PointerKind> History<T> for SharedList<T, P> {
+    fn push(&self, value: T) -> Self {
+        SharedList::push(self, value)
+    }
+
+    fn len(&self) -> usize {
+        SharedList::len(self)
+    }
+}
+
+pub struct Node<T, P: PointerKind = RcKind> {
     value: T,
-    previous: Option<Rc<Node<T>>>,
+    previous: Option<P::Pointer<Node<T, P>>>,
 }
 
-impl<T> Node<T> {
-    fn new(value: T, previous: Option<Rc<Node<T>>>) -> Node<T> {
+impl<T: PartialEq, P: PointerKind> PartialEq for Node<T, P> {
+    fn eq(&self, other: &Self) -> bool {
+        Iter { next: Some(self) }.eq(Iter { next: Some(other) })
+    }
+}
+
+impl<T: Eq, P: PointerKind> Eq for Node<T, P> {}
+
+impl<T, P: PointerKind> Node<T, P> {
+    fn new(value: T, previous: Option<P::Pointer<Node<T, P>>>) -> Node<T, P> {
         Node { value, previous }
     }
 }
+
+// Letting the compiler drop `previous` would recurse once per node, which
+// overflows the stack on long histories. Instead unlink the chain here one
+// node at a time, stopping at the first node another list still shares.
+impl<T, P: PointerKind> Drop for Node<T, P> {
+    fn drop(&mut self) {
+        let mut previous = self.previous.take();
+        while let Some(pointer) = previous {
+            previous = match P::try_unwrap(pointer) {
+                Ok(mut node) => node.previous.take(),
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    pub use super::*;
+    use crate::{boxes::BFSBox, hanoi::Hanoi, machine::Machine, traits::Solver};
+
+    #[test]
+    fn shares_histories_across_threads_alright() {
+        let history: SyncSharedList<usize> = SharedList::new().push(1).push(2);
+        let branch = history.clone();
+
+        let extended = std::thread::spawn(move || branch.push(3)).join().unwrap();
+
+        assert_eq!(Vec::from(history), vec![1, 2]);
+        assert_eq!(Vec::from(extended), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn walks_histories_newest_first_alright() {
+        let history: SharedList<usize> = SharedList::new().push(1).push(2).push(3);
+
+        assert_eq!(history.iter().copied().collect::<Vec<_>>(), vec![3, 2, 1]);
+        assert_eq!(history.last(), Some(&3));
+        assert_eq!(history.head(), Some(&1));
+        assert_eq!(SharedList::<usize>::new().last(), None);
+    }
+
+    #[test]
+    fn drops_long_histories_alright() {
+        let mut history: SharedList<usize> = SharedList::new();
+        for i in 0..1_000_000 {
+            history = history.push(i);
+        }
+        let branch = history.push(0);
+
+        assert!(branch == history.push(0));
+        drop(history);
+        assert_eq!(branch.head(), Some(&0));
+        assert_eq!(branch.iter().count(), 1_000_001);
+    }
+
+    #[test]
+    fn solves_with_either_list_alright() {
+        let machine = Machine::new(Hanoi::<4>::new(), Hanoi::solved());
+        let local = Solver::<_>::find_one::<BFSBox<_>>(machine.clone()).unwrap();
+        let worker = std::thread::spawn(move || {
+            Solver::<_>::find_one::<BFSBox<_, SyncSharedList<_>>>(machine).unwrap()
+        });
+
+        assert_eq!(worker.join().unwrap(), local);
+    }
+}
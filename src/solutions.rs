@@ -0,0 +1,330 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    marker::PhantomData,
+    time::Instant,
+};
+
+use crate::{
+    search::{Limit, SearchConfig, SearchOutcome, SearchReport, SearchStats, Solution},
+    traits::{Goal, History, Observer, State, StateBox},
+};
+
+// Holds a suspended `Machine` search. Each call to `next` resumes expanding
+// from where the last one stopped until another goal turns up.
+pub struct Solutions<S: State, G: Goal<S>, SB: StateBox<S>, O: Observer<S> = ()> {
+    goal: G,
+    config: SearchConfig,
+    observer: O,
+    started: Instant,
+    stats: SearchStats,
+    // The cheapest cost each state has been reached with so far. Boxes that
+    // pop by cost re-open a state whenever a cheaper path turns up, which
+    // keeps them optimal under inconsistent heuristics.
+    seen: HashMap<S, f64>,
+    unprocessed_states: SB,
+    pending: VecDeque<Solution<S>>,
+    // A goal accepted on pop is handed out before it is expanded, so its
+    // successors wait here for the next call.
+    popped_goal: Option<(S, SB::History, f64)>,
+    depth_pruned: bool,
+    outcome: Option<SearchOutcome<S>>,
+    _state: PhantomData<S>,
+}
+
+impl<S: State, G: Goal<S>, SB: StateBox<S>, O: Observer<S>> Solutions<S, G, SB, O> {
+    pub(crate) fn new(
+        init_state: S,
+        goal: G,
+        config: SearchConfig,
+        heuristic: SB::Heuristic,
+        mut observer: O,
+    ) -> Self {
+        let mut pending = VecDeque::new();
+        if !SB::GOAL_TEST_ON_POP && goal.is_complete(&init_state) {
+            let solution = Solution {
+                path: vec![init_state.clone()],
+                cost: 0f64,
+            };
+            observer.goal_found(&solution);
+            pending.push_back(solution);
+        }
+
+        let mut seen = HashMap::new();
+        seen.insert(init_state.clone(), 0f64);
+        let mut unprocessed_states = SB::init(init_state, heuristic);
+        unprocessed_states.configure(&config);
+        let stats = SearchStats {
+            suboptimality_bound: unprocessed_states.suboptimality_bound(),
+            ..SearchStats::default()
+        };
+
+        Solutions {
+            goal,
+            config,
+            observer,
+            started: Instant::now(),
+            stats,
+            seen,
+            unprocessed_states,
+            pending,
+            popped_goal: None,
+            depth_pruned: false,
+            outcome: None,
+            _state: PhantomData,
+        }
+    }
+
+    pub fn stats(&self) -> &SearchStats {
+        &self.stats
+    }
+
+    // Why the search stopped expanding: the space ran out, a limit was hit or
+    // it was cancelled. `None` while it can still turn up more solutions.
+    pub fn outcome(&self) -> Option<&SearchOutcome<S>> {
+        self.outcome.as_ref()
+    }
+
+    // Runs until the first solution, the way `Solver::search` reports it.
+    pub(crate) fn into_report(mut self) -> SearchReport<S> {
+        match self.next() {
+            Some(solution) => {
+                let depth = solution.path.len().saturating_sub(1);
+                self.stats.conclude(depth, self.started);
+                self.observer.finished(&self.stats);
+                SearchReport {
+                    outcome: SearchOutcome::Found(solution),
+                    stats: self.stats,
+                }
+            }
+            None => SearchReport {
+                outcome: self.outcome.unwrap_or(SearchOutcome::Exhausted),
+                stats: self.stats,
+            },
+        }
+    }
+
+    fn found(&mut self, solution: Solution<S>) {
+        self.observer.goal_found(&solution);
+        self.pending.push_back(solution);
+    }
+
+    fn finish(&mut self, outcome: SearchOutcome<S>) {
+        self.stats.conclude(self.stats.max_depth, self.started);
+        self.observer.finished(&self.stats);
+        self.outcome = Some(outcome);
+    }
+
+    fn expand(&mut self) {
+        let popped_goal = self.popped_goal.take();
+        let is_popped_goal = popped_goal.is_some();
+        let (state, history, cost) = match popped_goal.or_else(|| self.unprocessed_states.pop()) {
+            Some(entry) => entry,
+            None if self.depth_pruned => {
+                return self.finish(SearchOutcome::LimitReached(Limit::Depth))
+            }
+            None => return self.finish(SearchOutcome::Exhausted),
+        };
+
+        if SB::GOAL_TEST_ON_POP && !is_popped_goal {
+            if self.seen.get(&state).is_some_and(|&best| best < cost) {
+                self.stats.duplicates += 1;
+                self.observer.duplicate(&state);
+                return;
+            }
+            if self.goal.is_complete(&state) {
+                self.found(Solution {
+                    path: history.push(state.clone()).into(),
+                    cost,
+                });
+                self.popped_goal = Some((state, history, cost));
+                return;
+            }
+        }
+
+        if self.config.cancelled() {
+            return self.finish(SearchOutcome::Cancelled);
+        }
+        if let Some(limit) = self.config.exceeded(
+            self.stats.expanded,
+            self.unprocessed_states.len(),
+            self.started,
+        ) {
+            return self.finish(SearchOutcome::LimitReached(limit));
+        }
+        if self.config.too_deep(history.len()) {
+            self.depth_pruned = true;
+            return;
+        }
+
+        self.stats.expanded += 1;
+        self.observer.popped(&state, history.len());
+        for (next_state, step_cost) in state.next_weighted() {
+            let new_history = history.push(state.clone());
+            let new_cost = cost + step_cost;
+            self.stats.generated += 1;
+            self.stats.max_depth = self.stats.max_depth.max(new_history.len());
+            self.observer
+                .generated(&next_state, new_history.len(), new_cost);
+            if SB::GOAL_TEST_ON_POP {
+                match self.seen.get(&next_state) {
+                    Some(&best) if best <= new_cost => {
+                        self.stats.duplicates += 1;
+                        self.observer.duplicate(&next_state);
+                    }
+                    previous => {
+                        if previous.is_some() {
+                            self.stats.reopened += 1;
+                        }
+                        self.seen.insert(next_state.clone(), new_cost);
+                        self.unprocessed_states
+                            .insert(next_state, new_history, new_cost);
+                    }
+                }
+                continue;
+            }
+            if self.goal.is_complete(&next_state) {
+                self.found(Solution {
+                    path: new_history.push(next_state.clone()).into(),
+                    cost: new_cost,
+                });
+            }
+            if self.seen.contains_key(&next_state) {
+                self.stats.duplicates += 1;
+                self.observer.duplicate(&next_state);
+            } else {
+                self.seen.insert(next_state.clone(), new_cost);
+                self.unprocessed_states
+                    .insert(next_state, new_history, new_cost);
+            }
+        }
+        self.stats.peak_frontier = self.stats.peak_frontier.max(self.unprocessed_states.len());
+        self.stats.peak_seen = self.stats.peak_seen.max(self.seen.len());
+    }
+}
+
+impl<S: State, G: Goal<S>, SB: StateBox<S>, O: Observer<S>> Iterator for Solutions<S, G, SB, O> {
+    type Item = Solution<S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(solution) = self.pending.pop_front() {
+                return Some(solution);
+            }
+            if self.outcome.is_some() {
+                return None;
+            }
+            self.expand();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    pub use super::*;
+    use crate::{
+        boxes::{BFSBox, UniformCostBox},
+        hanoi::Hanoi,
+        machine::Machine,
+        search::Cancellation,
+        testing::Junction,
+        traits::Solver,
+    };
+
+    #[derive(Default)]
+    struct Watcher {
+        goal_popped: bool,
+        finished: usize,
+    }
+
+    impl Observer<Hanoi<3>> for Watcher {
+        fn popped(&mut self, state: &Hanoi<3>, _depth: usize) {
+            self.goal_popped |= *state == Hanoi::solved();
+        }
+
+        fn finished(&mut self, _stats: &SearchStats) {
+            self.finished += 1;
+        }
+    }
+
+    #[test]
+    fn yields_solutions_lazily_alright() {
+        let machine = Machine::new(Hanoi::<3>::new(), Hanoi::solved());
+        let all = Solver::<_>::find_all::<BFSBox<_>>(machine.clone());
+
+        let mut solutions = machine.solutions::<BFSBox<_>>();
+        let first = solutions.next().unwrap();
+        let expanded = solutions.stats().expanded;
+
+        assert_eq!(first.path, all[0]);
+        assert_eq!(first.cost, 7f64);
+        assert!(expanded < 27);
+        assert_eq!(solutions.count() + 1, all.len());
+    }
+
+    #[test]
+    fn yields_the_cheapest_solution_first_alright() {
+        let machine = Machine::new(Junction(0), Junction(3));
+        let mut solutions = machine.clone().solutions::<UniformCostBox<_>>();
+        let first = solutions.next().unwrap();
+        assert_eq!(first.cost, 3f64);
+        assert_eq!(first.path.len(), 4);
+        assert_eq!(solutions.next(), None);
+        assert_eq!(solutions.outcome(), Some(&SearchOutcome::Exhausted));
+
+        let all = Solver::<_>::find_all::<UniformCostBox<_>>(machine);
+        assert_eq!(all, vec![first.path]);
+    }
+
+    #[test]
+    fn records_why_it_stopped_alright() {
+        let config = SearchConfig {
+            max_expansions: Some(3),
+            ..SearchConfig::default()
+        };
+        let machine = Machine::new(Hanoi::<4>::new(), Hanoi::solved()).with_config(config);
+        let mut solutions = machine.solutions::<BFSBox<_>>();
+        assert_eq!(solutions.outcome(), None);
+        assert_eq!(solutions.next(), None);
+        assert_eq!(
+            solutions.outcome(),
+            Some(&SearchOutcome::LimitReached(Limit::Expansions))
+        );
+
+        let cancellation = Cancellation::new();
+        cancellation.cancel();
+        let config = SearchConfig {
+            cancellation: Some(cancellation),
+            ..SearchConfig::default()
+        };
+        let machine = Machine::new(Hanoi::<4>::new(), Hanoi::solved()).with_config(config);
+        let mut solutions = machine.solutions::<BFSBox<_>>();
+        assert_eq!(solutions.next(), None);
+        assert_eq!(solutions.outcome(), Some(&SearchOutcome::Cancelled));
+    }
+
+    #[test]
+    fn hands_out_a_popped_goal_before_expanding_it_alright() {
+        let machine = Machine::new(Hanoi::<3>::new(), Hanoi::solved());
+        let mut watcher = Watcher::default();
+        let mut solutions = machine
+            .clone()
+            .solutions_observed::<UniformCostBox<_>, _>(&mut watcher);
+        let first = solutions.next().unwrap();
+        let expanded = solutions.stats().expanded;
+        drop(solutions);
+        assert!(!watcher.goal_popped);
+
+        // A budget that runs out on the very pop that finds the goal.
+        let config = SearchConfig {
+            max_expansions: Some(expanded),
+            ..SearchConfig::default()
+        };
+        let mut watcher = Watcher::default();
+        let report = machine
+            .with_config(config)
+            .search_observed::<UniformCostBox<_>, _>(&mut watcher);
+        assert_eq!(report.outcome, SearchOutcome::Found(first));
+        assert_eq!(report.stats.expanded, expanded);
+        assert_eq!(watcher.finished, 1);
+    }
+}
@@ -0,0 +1,49 @@
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{tileboard::TileBoard, traits::State, Coord};
+
+// Shuffles boards from a fixed seed, so that every run of a test searches
+// the same puzzles.
+pub(crate) fn boards<const C: usize, const R: usize>(
+    count: usize,
+    shuffles: usize,
+) -> Vec<TileBoard<C, R>> {
+    let mut rng = StdRng::seed_from_u64(17);
+    (0..count)
+        .map(|_| TileBoard::shuffled_with(shuffles, &mut rng))
+        .collect()
+}
+
+pub(crate) fn board<const C: usize, const R: usize>(shuffles: usize) -> TileBoard<C, R> {
+    boards(1, shuffles).remove(0)
+}
+
+// Whether every state in the path is one move on from the one before.
+pub(crate) fn is_valid_path<S: State>(path: &[S]) -> bool {
+    path.windows(2).all(|w| w[0].next().contains(&w[1]))
+}
+
+// 0 -> 3 directly costs 10, while 0 -> 1 -> 2 -> 3 costs 3.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub(crate) struct Junction(pub(crate) usize);
+
+impl State for Junction {
+    type Point = Coord;
+
+    fn next(&self) -> Vec<Self> {
+        self.next_weighted().into_iter().map(|(j, _)| j).collect()
+    }
+
+    fn differences(&self) -> Vec<(Self::Point, Self::Point)> {
+        vec![(Coord::new(self.0, 0), Coord::new(3, 0))]
+    }
+
+    fn next_weighted(&self) -> Vec<(Self, f64)> {
+        match self.0 {
+            0 => vec![(Junction(3), 10f64), (Junction(1), 1f64)],
+            1 => vec![(Junction(2), 1f64)],
+            2 => vec![(Junction(3), 1f64)],
+            _ => vec![],
+        }
+    }
+}
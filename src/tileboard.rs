@@ -1,7 +1,7 @@
 use std::fmt::Debug;
 
 use arrayvec::ArrayVec;
-use rand::prelude::SliceRandom;
+use rand::{prelude::SliceRandom, Rng};
 
 use crate::{Coord, traits::State};
 
@@ -27,11 +27,14 @@ impl<const C: usize, const R: usize> Default for TileBoard<C, R> {
 
 impl<const C: usize, const R: usize> TileBoard<C, R> {
     pub fn shuffled(shuffles: usize) -> Self {
+        Self::shuffled_with(shuffles, &mut rand::thread_rng())
+    }
+
+    pub fn shuffled_with<Rn: Rng>(shuffles: usize, r: &mut Rn) -> Self {
         let mut res: TileBoard<C, R> = TileBoard::default();
 
         let (mut pos_x, mut pos_y) = (C - 1, R - 1);
 
-        let mut r = rand::thread_rng();
         let mut move_options: ArrayVec<(usize, usize), 4> = ArrayVec::new();
         (0..shuffles).for_each(|_| {
             move_options.clear();
@@ -52,7 +55,7 @@ impl<const C: usize, const R: usize> TileBoard<C, R> {
                 move_options.push((pos_x, pos_y + 1));
             }
 
-            let (c_x, c_y) = *move_options.choose(&mut r).unwrap();
+            let (c_x, c_y) = *move_options.choose(r).unwrap();
             let t = res.inner[c_y][c_x];
             res.inner[c_y][c_x] = res.inner[pos_y][pos_x];
             res.inner[pos_y][pos_x] = t;
@@ -62,6 +65,10 @@ impl<const C: usize, const R: usize> TileBoard<C, R> {
 
         res
     }
+
+    pub fn tile(&self, column: usize, row: usize) -> usize {
+        self.inner[row][column]
+    }
 }
 
 impl<const C: usize, const R: usize> State for TileBoard<C, R> {
@@ -1,24 +1,166 @@
 use std::hash::Hash;
 
-use crate::sharedlist::SharedList;
+use crate::search::{SearchConfig, SearchReport, SearchStats, Solution};
 
 pub trait State: Hash + Eq + Sized + Clone {
     type Point;
 
     fn next(&self) -> Vec<Self>;
     fn differences(&self) -> Vec<(Self::Point, Self::Point)>;
+
+    fn next_weighted(&self) -> Vec<(Self, f64)> {
+        self.next().into_iter().map(|state| (state, 1f64)).collect()
+    }
+}
+
+pub trait Goal<S: State> {
+    fn is_complete(&self, state: &S) -> bool;
+}
+
+// Every callback defaults to doing nothing, and `()` observes nothing at all,
+// so unobserved searches compile down to the same loop as before.
+pub trait Observer<S: State> {
+    fn popped(&mut self, _state: &S, _depth: usize) {}
+    fn generated(&mut self, _state: &S, _depth: usize, _cost: f64) {}
+    fn duplicate(&mut self, _state: &S) {}
+    fn goal_found(&mut self, _solution: &Solution<S>) {}
+    fn finished(&mut self, _stats: &SearchStats) {}
+}
+
+impl<S: State> Observer<S> for () {}
+
+impl<S: State, O: Observer<S>> Observer<S> for &mut O {
+    fn popped(&mut self, state: &S, depth: usize) {
+        (**self).popped(state, depth)
+    }
+
+    fn generated(&mut self, state: &S, depth: usize, cost: f64) {
+        (**self).generated(state, depth, cost)
+    }
+
+    fn duplicate(&mut self, state: &S) {
+        (**self).duplicate(state)
+    }
+
+    fn goal_found(&mut self, solution: &Solution<S>) {
+        (**self).goal_found(solution)
+    }
+
+    fn finished(&mut self, stats: &SearchStats) {
+        (**self).finished(stats)
+    }
+}
+
+pub trait Solver<S: State>: Sized {
+    // Whatever hands boxes their heuristic, which is `()` for solvers that let
+    // boxes build theirs by default.
+    type Supplier;
+
+    fn search_observed<SB: StateBox<S>, O: Observer<S>>(self, observer: &mut O) -> SearchReport<S>
+    where
+        Self::Supplier: Supply<SB::Heuristic>;
+    fn find_all_observed<SB: StateBox<S>, O: Observer<S>>(self, observer: &mut O) -> Vec<Vec<S>>
+    where
+        Self::Supplier: Supply<SB::Heuristic>;
+
+    fn search<SB: StateBox<S>>(self) -> SearchReport<S>
+    where
+        Self::Supplier: Supply<SB::Heuristic>,
+    {
+        self.search_observed::<SB, _>(&mut ())
+    }
+
+    fn find_all<SB: StateBox<S>>(self) -> Vec<Vec<S>>
+    where
+        Self::Supplier: Supply<SB::Heuristic>,
+    {
+        self.find_all_observed::<SB, _>(&mut ())
+    }
+
+    fn find_solution<SB: StateBox<S>>(self) -> Option<(Solution<S>, usize)>
+    where
+        Self::Supplier: Supply<SB::Heuristic>,
+    {
+        self.search::<SB>().solution()
+    }
+
+    fn find_one_with_checks<SB: StateBox<S>>(self) -> Option<(Vec<S>, usize)>
+    where
+        Self::Supplier: Supply<SB::Heuristic>,
+    {
+        self.find_solution::<SB>()
+            .map(|(solution, checks)| (solution.path, checks))
+    }
+
+    fn find_one<SB: StateBox<S>>(self) -> Option<Vec<S>>
+    where
+        Self::Supplier: Supply<SB::Heuristic>,
+    {
+        self.find_one_with_checks::<SB>().map(|(res, _)| res)
+    }
 }
 
-pub trait Solver<S: State> {
-    fn find_one_with_checks<SB: StateBox<S>>(self) -> Option<(Vec<S>, usize)>;
-    fn find_one<SB: StateBox<S>>(self) -> Option<Vec<S>>;
-    fn find_all<SB: StateBox<S>>(self) -> Vec<Vec<S>>;
+// Hands a box the heuristic it orders states by. `()` builds the default
+// one, while `Given` clones a value the caller built, such as a loaded
+// pattern database.
+pub trait Supply<T> {
+    fn supply(&self) -> T;
+}
+
+impl<T: Default> Supply<T> for () {
+    fn supply(&self) -> T {
+        T::default()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Given<T>(pub T);
+
+impl<T: Clone> Supply<T> for Given<T> {
+    fn supply(&self) -> T {
+        self.0.clone()
+    }
+}
+
+pub trait History<T>: Clone + Default + PartialEq + Into<Vec<T>> {
+    fn push(&self, value: T) -> Self;
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 pub trait StateBox<S: State> {
-    fn init(state: S) -> Self;
-    fn insert(&mut self, state: S, history: SharedList<S>);
-    fn pop(&mut self) -> Option<(S, SharedList<S>)>;
+    type History: History<S>;
+    // What the box orders states by besides their cost, or `()` if nothing.
+    type Heuristic;
+
+    // Boxes that pop in order of accumulated cost set this so that goals are
+    // only accepted once popped, which keeps their solutions the cheapest.
+    const GOAL_TEST_ON_POP: bool = false;
+
+    fn init(state: S, heuristic: Self::Heuristic) -> Self;
+    // Searches only insert successors of the state they popped last, so
+    // informed boxes hand that state's estimate on to
+    // `Heuristic::estimate_from`.
+    fn insert(&mut self, state: S, history: Self::History, cost: f64);
+    fn pop(&mut self) -> Option<(S, Self::History, f64)>;
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Lets boxes with runtime settings, such as a beam width, pick them up
+    // from the search's config before the first pop.
+    fn configure(&mut self, _config: &SearchConfig) {}
+
+    // How far from the cheapest cost a solution popped from this box can be,
+    // as a factor, when the box can promise one.
+    fn suboptimality_bound(&self) -> Option<f64> {
+        None
+    }
 }
 
 pub trait Distance: Clone + Hash + Eq {
@@ -26,3 +168,31 @@ pub trait Distance: Clone + Hash + Eq {
 
     fn distance(a: Self::Point, b: Self::Point) -> f64;
 }
+
+// Estimates the cost left from a whole state to the goal. Every `Distance` is
+// one, summed over the state's differences.
+pub trait Heuristic<S: State> {
+    fn estimate(&self, state: &S) -> f64;
+
+    // Boxes that know which state they expanded last pass on its estimate and
+    // the cost of the step taken from it.
+    fn estimate_from(&self, state: &S, _parent_estimate: f64, _step_cost: f64) -> f64 {
+        self.estimate(state)
+    }
+}
+
+impl<S: State, D: Distance<Point = S::Point>> Heuristic<S> for D {
+    fn estimate(&self, state: &S) -> f64 {
+        state
+            .differences()
+            .into_iter()
+            .map(|(real, found)| D::distance(real, found))
+            .sum()
+    }
+}
+
+// An abstract state a pattern database can store a distance for. Ranks index
+// the database's table, so they should be small and close together.
+pub trait Pattern: State {
+    fn rank(&self) -> usize;
+}